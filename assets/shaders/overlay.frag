@@ -0,0 +1,10 @@
+#version 330 core
+
+in vec3 OverlayColor;
+
+out vec4 Color;
+
+void main()
+{
+    Color = vec4(OverlayColor, 1.0);
+}
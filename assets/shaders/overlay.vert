@@ -0,0 +1,12 @@
+#version 330 core
+
+layout (location = 0) in vec2 Position;
+layout (location = 1) in vec3 Color;
+
+out vec3 OverlayColor;
+
+void main()
+{
+    gl_Position = vec4(Position, 0.0, 1.0);
+    OverlayColor = Color;
+}
@@ -0,0 +1,45 @@
+#version 330 core
+
+layout (location = 0) in vec3 Position;
+layout (location = 1) in vec3 Normal;
+layout (location = 2) in vec2 Texture;
+layout (location = 3) in uvec4 JointIndices;
+layout (location = 4) in vec4 JointWeights;
+
+// Position and normal deltas of the morph targets, must match mesh::MAX_MORPH_TARGETS
+layout (location = 5) in vec3 MorphPositions[4];
+layout (location = 9) in vec3 MorphNormals[4];
+
+// Must match skin::MAX_JOINTS
+layout (std140) uniform Joints {
+    mat4 jointMatrices[128];
+};
+
+uniform mat4 camMatrix;
+uniform mat4 model;
+uniform float morphWeights[4];
+
+out VS_OUTPUT {
+    vec3 Normal;
+    vec2 Texture;
+} OUT;
+
+void main()
+{
+    vec3 position = Position;
+    vec3 normal = Normal;
+    for (int i = 0; i < 4; i++) {
+        position += morphWeights[i] * MorphPositions[i];
+        normal += morphWeights[i] * MorphNormals[i];
+    }
+
+    mat4 skinMatrix =
+        JointWeights.x * jointMatrices[JointIndices.x] +
+        JointWeights.y * jointMatrices[JointIndices.y] +
+        JointWeights.z * jointMatrices[JointIndices.z] +
+        JointWeights.w * jointMatrices[JointIndices.w];
+
+    gl_Position = camMatrix * model * skinMatrix * vec4(position, 1.0);
+    OUT.Normal = mat3(model * skinMatrix) * normal;
+    OUT.Texture = Texture;
+}
\ No newline at end of file
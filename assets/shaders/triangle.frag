@@ -1,7 +1,7 @@
 #version 330 core
 
 in VS_OUTPUT {
-    vec3 Color;
+    vec3 Normal;
     vec2 Texture;
 } IN;
 
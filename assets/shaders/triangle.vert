@@ -1,20 +1,33 @@
 #version 330 core
 
 layout (location = 0) in vec3 Position;
-layout (location = 1) in vec3 Color;
+layout (location = 1) in vec3 Normal;
 layout (location = 2) in vec2 Texture;
 
+// Position and normal deltas of the morph targets, must match mesh::MAX_MORPH_TARGETS
+layout (location = 5) in vec3 MorphPositions[4];
+layout (location = 9) in vec3 MorphNormals[4];
+
 
 uniform mat4 camMatrix;
+uniform mat4 model;
+uniform float morphWeights[4];
 
 out VS_OUTPUT {
-    vec3 Color;
+    vec3 Normal;
     vec2 Texture;
 } OUT;
 
 void main()
 {
-    gl_Position = camMatrix * vec4(Position.x, Position.y, Position.z, 1.0);
-    OUT.Color = Color;
+    vec3 position = Position;
+    vec3 normal = Normal;
+    for (int i = 0; i < 4; i++) {
+        position += morphWeights[i] * MorphPositions[i];
+        normal += morphWeights[i] * MorphNormals[i];
+    }
+
+    gl_Position = camMatrix * model * vec4(position, 1.0);
+    OUT.Normal = mat3(model) * normal;
     OUT.Texture = Texture;
 }
\ No newline at end of file
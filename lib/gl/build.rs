@@ -1,27 +1,36 @@
 extern crate gl_generator;
 
+mod error_check_gen;
+
 use gl_generator::{Registry, Fallbacks, StructGenerator, DebugStructGenerator, Api, Profile};
+use error_check_gen::ErrorCheckGenerator;
 use std::env;
 use std::fs::File;
 use std::path::Path;
 
 fn main() {
     let out_dir = env::var("OUT_DIR").unwrap();
-    let mut file_gl = File::create(&Path::new(&out_dir).join("bindings.rs")).unwrap();
+    let mut file_gl = File::create(Path::new(&out_dir).join("bindings.rs")).unwrap();
 
     let registry = Registry::new(Api::Gl, (4, 5), Profile::Core, Fallbacks::All, [
         "GL_NV_command_list",
     ]);
 
-    if env::var("CARGO_FEATURE_DEBUG").is_ok() {
+    if env::var("CARGO_FEATURE_TRACE").is_ok() {
+        // Prints every call and the errors it triggers
         registry.write_bindings(
             DebugStructGenerator,
             &mut file_gl
         ).unwrap();
+    } else if env::var("CARGO_FEATURE_DEBUG").is_ok() {
+        registry.write_bindings(
+            ErrorCheckGenerator,
+            &mut file_gl
+        ).unwrap();
     } else {
         registry.write_bindings(
             StructGenerator,
             &mut file_gl
         ).unwrap();
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,149 @@
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::ffi::CStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+use super::Gl;
+
+static NEXT_CONTEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    // Id and share group of the context current on this thread, 0 for none
+    static CURRENT_CONTEXT: Cell<(u64, u64)> = const { Cell::new((0, 0)) };
+}
+
+/// Identity of the context a `Gl` was loaded for
+pub(crate) struct Context {
+    id : u64,
+    /// Id of the first context of the group, buffers, textures, shaders and programs
+    /// are shared by the contexts of a group, vertex arrays, framebuffers and queries are not
+    share_group : u64,
+    alive : AtomicBool,
+    capabilities : OnceLock<Capabilities>,
+}
+
+/// What the driver supports, queried the first time it is needed
+struct Capabilities {
+    version : (i32, i32),
+    extensions : HashSet<String>,
+}
+
+impl Capabilities {
+    fn query(gl : &Gl) -> Capabilities {
+        let mut major = 0;
+        let mut minor = 0;
+        let mut count = 0;
+        unsafe {
+            gl.GetIntegerv(super::MAJOR_VERSION, &mut major);
+            gl.GetIntegerv(super::MINOR_VERSION, &mut minor);
+            gl.GetIntegerv(super::NUM_EXTENSIONS, &mut count);
+        }
+
+        let extensions = (0..count.max(0) as u32)
+            .filter_map(|index| {
+                let name = unsafe { gl.GetStringi(super::EXTENSIONS, index) };
+                if name.is_null() {
+                    None
+                } else {
+                    Some(unsafe { CStr::from_ptr(name as *const _) }.to_string_lossy().into_owned())
+                }
+            })
+            .collect();
+
+        Capabilities {
+            version : (major, minor),
+            extensions,
+        }
+    }
+}
+
+impl Context {
+    pub(crate) fn new(shared_with : Option<&Context>) -> Context {
+        let id = NEXT_CONTEXT_ID.fetch_add(1, Ordering::Relaxed);
+
+        Context {
+            id,
+            share_group : shared_with.map_or(id, |context| context.share_group),
+            alive : AtomicBool::new(true),
+            capabilities : OnceLock::new(),
+        }
+    }
+}
+
+/// Context tracking, objects keep the `Gl` they were created with and check it when used
+impl Gl {
+    pub fn context_id(&self) -> u64 {
+        self.context.id
+    }
+
+    /// Version of the context, e.g. (4, 1), it can be lower than the one the bindings were generated for
+    pub fn version(&self) -> (i32, i32) {
+        self.capabilities().version
+    }
+
+    pub fn supports_version(&self, major : i32, minor : i32) -> bool {
+        self.version() >= (major, minor)
+    }
+
+    /// `name` is the full extension name, e.g. "GL_ARB_clip_control"
+    pub fn has_extension(&self, name : &str) -> bool {
+        self.capabilities().extensions.contains(name)
+    }
+
+    /// Loaded functions don't mean the context provides them, check the version or the extension
+    fn capabilities(&self) -> &Capabilities {
+        self.context.capabilities.get_or_init(|| Capabilities::query(self))
+    }
+
+    pub fn share_group(&self) -> u64 {
+        self.context.share_group
+    }
+
+    /// True if both can use the same buffers, textures, shaders and programs
+    pub fn shares_objects_with(&self, other : &Gl) -> bool {
+        self.context.share_group == other.context.share_group
+    }
+
+    /// Call after making the context current on this thread, `load_with` does it for the new context
+    pub fn mark_current(&self) {
+        CURRENT_CONTEXT.with(|current| current.set((self.context.id, self.context.share_group)));
+    }
+
+    /// Call after releasing the context from this thread
+    pub fn mark_released(&self) {
+        if self.is_current() {
+            CURRENT_CONTEXT.with(|current| current.set((0, 0)));
+        }
+    }
+
+    pub fn is_current(&self) -> bool {
+        CURRENT_CONTEXT.with(|current| current.get().0 == self.context.id)
+    }
+
+    /// Call when the context is destroyed, objects still using it trip the debug assertions
+    pub fn mark_destroyed(&self) {
+        self.context.alive.store(false, Ordering::Relaxed);
+        self.mark_released();
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.context.alive.load(Ordering::Relaxed)
+    }
+
+    /// Checks in debug builds that the context is alive and current on this thread
+    #[track_caller]
+    #[inline]
+    pub fn debug_assert_current(&self) {
+        if cfg!(debug_assertions) {
+            assert!(self.is_alive(), "GL context {} used after being destroyed", self.context.id);
+
+            let (current, _) = CURRENT_CONTEXT.with(|current| current.get());
+            assert!(
+                current == self.context.id,
+                "object of GL context {} used while context {} is current on this thread",
+                self.context.id, current
+            );
+        }
+    }
+}
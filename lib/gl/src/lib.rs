@@ -2,24 +2,60 @@ mod bindings {
     include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 }
 
+mod context;
+mod state;
+
 use std::ops::Deref;
 
 pub use bindings::*;
+pub use state::StateCounters;
+
+use std::sync::Arc;
+
+use context::Context;
+use state::StateCache;
 
-use std::rc::Rc;
+/// Function pointers of a context, they never change once loaded so any thread can read them
+struct Functions(bindings::Gl);
 
+unsafe impl Sync for Functions {}
+
+/// Handle to the functions and the state of one context, cheap to clone and `Send`
+/// Calls through it are only valid on the thread the context is current on
 #[derive(Clone)]
 pub struct Gl {
-    inner: Rc<bindings::Gl>,
+    inner: Arc<Functions>,
+    state: Arc<StateCache>,
+    context: Arc<Context>,
 }
 
 impl Gl {
+    /// Loads the functions of the context current on this thread
     pub fn load_with<F>(loadfn: F) -> Gl
         where F: FnMut(&'static str) -> *const types::GLvoid
     {
-        Gl {
-            inner: Rc::new(bindings::Gl::load_with(loadfn))
-        }
+        Gl::load(loadfn, Context::new(None))
+    }
+
+    /// For a context created to share its objects with this one (SDL_GL_SHARE_WITH_CURRENT_CONTEXT),
+    /// current on this thread
+    pub fn load_shared_with<F>(&self, loadfn: F) -> Gl
+        where F: FnMut(&'static str) -> *const types::GLvoid
+    {
+        Gl::load(loadfn, Context::new(Some(&self.context)))
+    }
+
+    fn load<F>(loadfn: F, context: Context) -> Gl
+        where F: FnMut(&'static str) -> *const types::GLvoid
+    {
+        let gl = Gl {
+            inner: Arc::new(Functions(bindings::Gl::load_with(loadfn))),
+            state: Arc::new(StateCache::default()),
+            context: Arc::new(context),
+        };
+        gl.mark_current();
+
+        gl
     }
 }
 
@@ -27,6 +63,67 @@ impl Deref for Gl {
     type Target = bindings::Gl;
 
     fn deref(&self) -> &bindings::Gl {
-        &self.inner
+        &self.inner.0
     }
-}
\ No newline at end of file
+}
+
+#[cfg(feature = "debug")]
+mod error_check {
+    use std::backtrace::Backtrace;
+    use std::sync::atomic::{AtomicU8, Ordering};
+
+    use super::types::GLenum;
+
+    /// What happens when a call leaves an error behind
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ErrorMode {
+        Panic,
+        /// Prints the error and a backtrace on stderr and carries on
+        Log,
+    }
+
+    static ERROR_MODE: AtomicU8 = AtomicU8::new(0);
+
+    pub fn set_error_mode(mode : ErrorMode) {
+        ERROR_MODE.store(mode as u8, Ordering::Relaxed);
+    }
+
+    pub fn error_mode() -> ErrorMode {
+        match ERROR_MODE.load(Ordering::Relaxed) {
+            0 => ErrorMode::Panic,
+            _ => ErrorMode::Log,
+        }
+    }
+
+    pub fn error_name(error : GLenum) -> &'static str {
+        match error {
+            super::INVALID_ENUM => "GL_INVALID_ENUM",
+            super::INVALID_VALUE => "GL_INVALID_VALUE",
+            super::INVALID_OPERATION => "GL_INVALID_OPERATION",
+            super::INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
+            super::OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
+            super::STACK_UNDERFLOW => "GL_STACK_UNDERFLOW",
+            super::STACK_OVERFLOW => "GL_STACK_OVERFLOW",
+            _ => "unknown GL error",
+        }
+    }
+
+    /// Called by the bindings when glGetError reports something after `function`
+    /// The trace bindings report the errors themselves
+    #[cold]
+    #[cfg_attr(feature = "trace", allow(dead_code))]
+    pub(crate) fn on_gl_error(function : &'static str, error : GLenum) {
+        let message = format!("{} failed with {} (0x{:04X})", function, error_name(error), error);
+
+        match error_mode() {
+            ErrorMode::Panic => panic!("{}", message),
+            ErrorMode::Log => eprintln!("[GL] {}\n{}", message, Backtrace::force_capture()),
+        }
+    }
+}
+
+#[cfg(feature = "debug")]
+pub use error_check::{ErrorMode, set_error_mode, error_mode, error_name};
+
+#[cfg(all(feature = "debug", not(feature = "trace")))]
+use error_check::on_gl_error;
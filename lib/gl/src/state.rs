@@ -0,0 +1,429 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use super::types::{GLenum, GLint, GLsizei, GLuint};
+use super::Gl;
+
+/// Number of state calls sent to the driver and skipped because they changed nothing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StateCounters {
+    pub issued : u64,
+    pub skipped : u64,
+}
+
+impl StateCounters {
+    /// True if a counter is lower than in `other`, e.g. they were reset since `other` was taken
+    pub fn is_before(&self, other : &StateCounters) -> bool {
+        self.issued < other.issued || self.skipped < other.skipped
+    }
+}
+
+/// Saturates at 0 if the counters were reset in between, see `is_before`
+impl std::ops::Sub for StateCounters {
+    type Output = StateCounters;
+
+    fn sub(self, other : StateCounters) -> StateCounters {
+        StateCounters {
+            issued : self.issued.saturating_sub(other.issued),
+            skipped : self.skipped.saturating_sub(other.skipped),
+        }
+    }
+}
+
+/// Last state set through the `Gl` state functions
+/// A missing value means unknown, the next call setting it always reaches the driver
+#[derive(Default)]
+pub(crate) struct CachedState {
+    program : Option<GLuint>,
+    vertex_array : Option<GLuint>,
+    buffers : HashMap<GLenum, GLuint>,
+    /// Index of the active unit (0 for GL_TEXTURE0)
+    active_texture : Option<GLuint>,
+    /// By unit and target
+    textures : HashMap<(GLuint, GLenum), GLuint>,
+    capabilities : HashMap<GLenum, bool>,
+    depth_mask : Option<bool>,
+    depth_func : Option<GLenum>,
+    /// Source and destination factors for the color then the alpha
+    blend_func : Option<(GLenum, GLenum, GLenum, GLenum)>,
+    blend_equation : Option<GLenum>,
+    cull_face : Option<GLenum>,
+    front_face : Option<GLenum>,
+    polygon_mode : Option<GLenum>,
+    scissor : Option<(GLint, GLint, GLsizei, GLsizei)>,
+    /// Function, reference and read mask
+    stencil_func : Option<(GLenum, GLint, GLuint)>,
+    /// Stencil fail, depth fail and pass operations
+    stencil_op : Option<(GLenum, GLenum, GLenum)>,
+    stencil_mask : Option<GLuint>,
+    counters : StateCounters,
+}
+
+impl CachedState {
+    /// Counts the call, true if it has to be sent to the driver
+    fn needs_call(&mut self, changed : bool) -> bool {
+        if changed {
+            self.counters.issued += 1;
+        } else {
+            self.counters.skipped += 1;
+        }
+
+        changed
+    }
+}
+
+/// Cache of one context, shared by every clone of its `Gl`
+/// Only the thread the context is current on uses it, the lock is never contended
+#[derive(Default)]
+pub(crate) struct StateCache {
+    state : Mutex<CachedState>,
+}
+
+impl StateCache {
+    fn lock(&self) -> MutexGuard<'_, CachedState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Stores `value` in the field, true if it differs from the cached one and has to be sent to the driver
+    fn update<T, F>(&self, field : F, value : T) -> bool
+        where T : Copy + PartialEq,
+              F : FnOnce(&mut CachedState) -> &mut Option<T>
+    {
+        let mut state = self.lock();
+        let cached = field(&mut state);
+        let changed = *cached != Some(value);
+        *cached = Some(value);
+
+        state.needs_call(changed)
+    }
+
+    /// Same as `update` for the values kept by key
+    fn update_entry<K, F>(&self, map : F, key : K, value : GLuint) -> bool
+        where K : std::hash::Hash + Eq,
+              F : FnOnce(&mut CachedState) -> &mut HashMap<K, GLuint>
+    {
+        let mut state = self.lock();
+        let changed = map(&mut state).insert(key, value) != Some(value);
+
+        state.needs_call(changed)
+    }
+}
+
+/// State changes going through the cache, redundant ones are skipped
+///
+/// State set with the raw bindings (`gl.BindBuffer`...) is not seen by the cache,
+/// call `invalidate_state` after doing so
+/// In debug builds they check that the context is the one current on the calling thread
+impl Gl {
+    pub fn use_program(&self, program : GLuint) {
+        self.debug_assert_current();
+        if self.state.update(|state| &mut state.program, program) {
+            unsafe { self.UseProgram(program); }
+        }
+    }
+
+    pub fn bind_vertex_array(&self, vertex_array : GLuint) {
+        self.debug_assert_current();
+        if self.state.update(|state| &mut state.vertex_array, vertex_array) {
+            unsafe { self.BindVertexArray(vertex_array); }
+
+            // The element buffer binding belongs to the vertex array
+            self.state.lock().buffers.remove(&super::ELEMENT_ARRAY_BUFFER);
+        }
+    }
+
+    pub fn bind_buffer(&self, target : GLenum, buffer : GLuint) {
+        self.debug_assert_current();
+        if self.state.update_entry(|state| &mut state.buffers, target, buffer) {
+            unsafe { self.BindBuffer(target, buffer); }
+        }
+    }
+
+    /// Binds to the indexed binding point `index` of `target`, and to `target` itself like GL does
+    pub fn bind_buffer_base(&self, target : GLenum, index : GLuint, buffer : GLuint) {
+        self.debug_assert_current();
+        unsafe { self.BindBufferBase(target, index, buffer); }
+
+        let mut state = self.state.lock();
+        state.needs_call(true);
+        state.buffers.insert(target, buffer);
+    }
+
+    /// `unit` is the index of the unit, 0 for GL_TEXTURE0
+    pub fn active_texture(&self, unit : GLuint) {
+        self.debug_assert_current();
+        if self.state.update(|state| &mut state.active_texture, unit) {
+            unsafe { self.ActiveTexture(super::TEXTURE0 + unit); }
+        }
+    }
+
+    /// Binds to the active texture unit
+    pub fn bind_texture(&self, target : GLenum, texture : GLuint) {
+        self.debug_assert_current();
+
+        let changed = {
+            let mut state = self.state.lock();
+            let changed = match state.active_texture {
+                Some(unit) => state.textures.insert((unit, target), texture) != Some(texture),
+                // Unknown unit, bind without caching
+                None => true,
+            };
+            state.needs_call(changed)
+        };
+
+        if changed {
+            unsafe { self.BindTexture(target, texture); }
+        }
+    }
+
+    /// Makes `unit` active and binds the texture to it
+    pub fn bind_texture_unit(&self, unit : GLuint, target : GLenum, texture : GLuint) {
+        self.active_texture(unit);
+        self.bind_texture(target, texture);
+    }
+
+    pub fn enable(&self, capability : GLenum) {
+        self.set_enabled(capability, true);
+    }
+
+    pub fn disable(&self, capability : GLenum) {
+        self.set_enabled(capability, false);
+    }
+
+    pub fn set_enabled(&self, capability : GLenum, enabled : bool) {
+        self.debug_assert_current();
+
+        let changed = {
+            let mut state = self.state.lock();
+            let changed = state.capabilities.insert(capability, enabled) != Some(enabled);
+            state.needs_call(changed)
+        };
+
+        if changed {
+            unsafe {
+                if enabled {
+                    self.Enable(capability);
+                } else {
+                    self.Disable(capability);
+                }
+            }
+        }
+    }
+
+    pub fn depth_mask(&self, write : bool) {
+        self.debug_assert_current();
+        if self.state.update(|state| &mut state.depth_mask, write) {
+            unsafe { self.DepthMask(if write { super::TRUE } else { super::FALSE }); }
+        }
+    }
+
+    pub fn depth_func(&self, func : GLenum) {
+        self.debug_assert_current();
+        if self.state.update(|state| &mut state.depth_func, func) {
+            unsafe { self.DepthFunc(func); }
+        }
+    }
+
+    /// Same factors for the color and the alpha
+    pub fn blend_func(&self, source : GLenum, destination : GLenum) {
+        self.blend_func_separate(source, destination, source, destination);
+    }
+
+    pub fn blend_func_separate(&self, source_rgb : GLenum, destination_rgb : GLenum, source_alpha : GLenum, destination_alpha : GLenum) {
+        self.debug_assert_current();
+        let factors = (source_rgb, destination_rgb, source_alpha, destination_alpha);
+        if self.state.update(|state| &mut state.blend_func, factors) {
+            unsafe { self.BlendFuncSeparate(source_rgb, destination_rgb, source_alpha, destination_alpha); }
+        }
+    }
+
+    pub fn blend_equation(&self, equation : GLenum) {
+        self.debug_assert_current();
+        if self.state.update(|state| &mut state.blend_equation, equation) {
+            unsafe { self.BlendEquation(equation); }
+        }
+    }
+
+    pub fn cull_face(&self, face : GLenum) {
+        self.debug_assert_current();
+        if self.state.update(|state| &mut state.cull_face, face) {
+            unsafe { self.CullFace(face); }
+        }
+    }
+
+    pub fn front_face(&self, winding : GLenum) {
+        self.debug_assert_current();
+        if self.state.update(|state| &mut state.front_face, winding) {
+            unsafe { self.FrontFace(winding); }
+        }
+    }
+
+    /// For both faces, the only choice in a core profile
+    pub fn polygon_mode(&self, mode : GLenum) {
+        self.debug_assert_current();
+        if self.state.update(|state| &mut state.polygon_mode, mode) {
+            unsafe { self.PolygonMode(super::FRONT_AND_BACK, mode); }
+        }
+    }
+
+    pub fn scissor(&self, x : GLint, y : GLint, width : GLsizei, height : GLsizei) {
+        self.debug_assert_current();
+        if self.state.update(|state| &mut state.scissor, (x, y, width, height)) {
+            unsafe { self.Scissor(x, y, width, height); }
+        }
+    }
+
+    /// For both faces
+    pub fn stencil_func(&self, func : GLenum, reference : GLint, mask : GLuint) {
+        self.debug_assert_current();
+        if self.state.update(|state| &mut state.stencil_func, (func, reference, mask)) {
+            unsafe { self.StencilFunc(func, reference, mask); }
+        }
+    }
+
+    pub fn stencil_op(&self, stencil_fail : GLenum, depth_fail : GLenum, pass : GLenum) {
+        self.debug_assert_current();
+        if self.state.update(|state| &mut state.stencil_op, (stencil_fail, depth_fail, pass)) {
+            unsafe { self.StencilOp(stencil_fail, depth_fail, pass); }
+        }
+    }
+
+    pub fn stencil_mask(&self, mask : GLuint) {
+        self.debug_assert_current();
+        if self.state.update(|state| &mut state.stencil_mask, mask) {
+            unsafe { self.StencilMask(mask); }
+        }
+    }
+
+    /// Asks the driver only the first time
+    pub fn is_enabled(&self, capability : GLenum) -> bool {
+        self.debug_assert_current();
+        if let Some(enabled) = self.state.lock().capabilities.get(&capability) {
+            return *enabled;
+        }
+
+        let enabled = unsafe { self.IsEnabled(capability) } == super::TRUE;
+        self.state.lock().capabilities.insert(capability, enabled);
+
+        enabled
+    }
+
+    pub fn delete_program(&self, program : GLuint) {
+        self.debug_assert_current();
+        unsafe { self.DeleteProgram(program); }
+
+        // A program in use is only deleted once another one replaces it, forget it to be safe
+        let mut state = self.state.lock();
+        if state.program == Some(program) {
+            state.program = None;
+        }
+    }
+
+    /// GL binds 0 in place of a deleted object, the cache does the same
+    /// The caches of the other contexts of the share group are not updated
+    pub fn delete_vertex_array(&self, vertex_array : GLuint) {
+        self.debug_assert_current();
+        unsafe { self.DeleteVertexArrays(1, &vertex_array); }
+
+        let mut state = self.state.lock();
+        if state.vertex_array == Some(vertex_array) {
+            state.vertex_array = Some(0);
+            state.buffers.remove(&super::ELEMENT_ARRAY_BUFFER);
+        }
+    }
+
+    pub fn delete_buffer(&self, buffer : GLuint) {
+        self.debug_assert_current();
+        unsafe { self.DeleteBuffers(1, &buffer); }
+
+        for bound in self.state.lock().buffers.values_mut() {
+            if *bound == buffer {
+                *bound = 0;
+            }
+        }
+    }
+
+    pub fn delete_texture(&self, texture : GLuint) {
+        self.debug_assert_current();
+        unsafe { self.DeleteTextures(1, &texture); }
+
+        for bound in self.state.lock().textures.values_mut() {
+            if *bound == texture {
+                *bound = 0;
+            }
+        }
+    }
+
+    /// Forgets the cached state, e.g. after raw GL calls or code outside this crate changed it
+    pub fn invalidate_state(&self) {
+        let mut state = self.state.lock();
+        let counters = state.counters;
+        *state = CachedState { counters, ..CachedState::default() };
+    }
+
+    /// Totals since the context was created or `reset_state_counters`
+    pub fn state_counters(&self) -> StateCounters {
+        self.state.lock().counters
+    }
+
+    pub fn reset_state_counters(&self) {
+        self.state.lock().counters = StateCounters::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CachedState, StateCache, StateCounters};
+
+    #[test]
+    fn counters_difference() {
+        let before = StateCounters { issued : 3, skipped : 5 };
+        let after = StateCounters { issued : 10, skipped : 5 };
+
+        assert_eq!(after - before, StateCounters { issued : 7, skipped : 0 });
+        assert!(!after.is_before(&before));
+        assert!(!before.is_before(&before));
+    }
+
+    #[test]
+    fn counters_reset_in_between() {
+        let before = StateCounters { issued : 3, skipped : 5 };
+        let after = StateCounters { issued : 4, skipped : 1 };
+
+        assert!(after.is_before(&before));
+        assert_eq!(after - before, StateCounters { issued : 1, skipped : 0 });
+    }
+
+    #[test]
+    fn needs_call_counts() {
+        let mut state = CachedState::default();
+
+        assert!(state.needs_call(true));
+        assert!(!state.needs_call(false));
+        assert!(!state.needs_call(false));
+        assert_eq!(state.counters, StateCounters { issued : 1, skipped : 2 });
+    }
+
+    #[test]
+    fn update_skips_the_cached_value() {
+        let cache = StateCache::default();
+
+        // Unknown at first
+        assert!(cache.update(|state| &mut state.program, 0));
+        assert!(!cache.update(|state| &mut state.program, 0));
+        assert!(cache.update(|state| &mut state.program, 7));
+        assert_eq!(cache.lock().program, Some(7));
+        assert_eq!(cache.lock().counters, StateCounters { issued : 2, skipped : 1 });
+    }
+
+    #[test]
+    fn update_entry_is_per_key() {
+        let cache = StateCache::default();
+
+        assert!(cache.update_entry(|state| &mut state.buffers, 1, 4));
+        assert!(cache.update_entry(|state| &mut state.buffers, 2, 4));
+        assert!(!cache.update_entry(|state| &mut state.buffers, 1, 4));
+        assert!(cache.update_entry(|state| &mut state.buffers, 1, 5));
+        assert_eq!(cache.lock().counters, StateCounters { issued : 3, skipped : 1 });
+    }
+}
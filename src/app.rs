@@ -0,0 +1,168 @@
+use std::time::{Duration, Instant};
+
+use sdl2::event::Event;
+use sdl2::video::SwapInterval;
+
+use crate::graphics::profiler::FrameProfiler;
+use crate::input::{Input, InputMap};
+use crate::windsdl::Winsdl;
+
+/// How the runner paces the frames
+#[derive(Debug, Clone, Copy)]
+pub struct LoopSettings {
+    /// Seconds simulated by each `fixed_update`
+    pub fixed_timestep: f32,
+    /// Fixed updates allowed per frame, the simulation slows down past it instead of never catching up
+    pub max_fixed_steps: u32,
+    /// Frames per second not to exceed, `None` to render as fast as possible (or at the vsync rate)
+    pub frame_limit: Option<f32>,
+}
+
+impl Default for LoopSettings {
+    fn default() -> LoopSettings {
+        LoopSettings {
+            fixed_timestep: 1.0 / 60.0,
+            max_fixed_steps: 5,
+            frame_limit: None,
+        }
+    }
+}
+
+/// Everything the runner shares with the application
+pub struct AppContext {
+    pub windsdl: Winsdl,
+    pub gl: gl::Gl,
+    /// Fed with every event before `App::on_event`, cleared at the start of each frame
+    pub input: Input,
+    pub settings: LoopSettings,
+    /// Times every frame, from the start of the frame to the buffer swap
+    pub profiler: FrameProfiler,
+    /// Seconds since the start of the loop
+    pub time: f32,
+    pub frame: u64,
+    quit_requested: bool,
+}
+
+impl AppContext {
+    /// Stops the loop at the end of the current frame
+    pub fn quit(&mut self) {
+        self.quit_requested = true;
+    }
+
+    pub fn set_vsync(&mut self, vsync: bool) -> Result<(), String> {
+        let interval = if vsync { SwapInterval::VSync } else { SwapInterval::Immediate };
+        self.windsdl.video_subsystem.gl_set_swap_interval(interval)
+    }
+
+    pub fn vsync(&self) -> bool {
+        self.windsdl.video_subsystem.gl_get_swap_interval() != SwapInterval::Immediate
+    }
+}
+
+/// A program driven by `run`
+///
+/// Each frame: `on_event` for every event, `fixed_update` as many times as the fixed timestep fits
+/// in the elapsed time, `update` once, then `render`
+pub trait App: Sized {
+    /// Creates the application once the window and the GL context exist
+    fn init(context: &mut AppContext) -> Result<Self, String>;
+
+    /// `Event::Quit` stops the loop unless this is overridden
+    fn on_event(&mut self, context: &mut AppContext, event: &Event) {
+        if let Event::Quit { .. } = event {
+            context.quit();
+        }
+    }
+
+    /// Simulation step, always `dt = LoopSettings::fixed_timestep` seconds
+    fn fixed_update(&mut self, _context: &mut AppContext, _dt: f32) {}
+
+    /// Called once per frame with the frame time in seconds
+    fn update(&mut self, context: &mut AppContext, dt: f32);
+
+    /// `alpha` is how far the time is between the last two fixed updates (0 to 1),
+    /// to interpolate what they simulate
+    fn render(&mut self, context: &mut AppContext, alpha: f32);
+
+    /// Called once after the last frame, the GL context is still alive
+    fn shutdown(&mut self, _context: &mut AppContext) {}
+}
+
+/// Longest frame time taken into account, e.g. after a breakpoint or while the window is dragged
+const MAX_FRAME_TIME: f32 = 0.25;
+
+/// Frames the profiler statistics are computed over
+const PROFILED_FRAMES: usize = 240;
+
+/// Runs the application until it calls `AppContext::quit`
+pub fn run<A: App>(windsdl: Winsdl, settings: LoopSettings) -> Result<(), String> {
+    let mut context = AppContext {
+        gl: windsdl.gl.clone(),
+        profiler: FrameProfiler::new(&windsdl.gl, PROFILED_FRAMES),
+        windsdl,
+        input: Input::new(InputMap::defaults()),
+        settings,
+        time: 0.0,
+        frame: 0,
+        quit_requested: false,
+    };
+
+    let mut app = A::init(&mut context)?;
+
+    let start = Instant::now();
+    let mut last_frame = start;
+    let mut accumulator = 0.0;
+
+    while !context.quit_requested {
+        let frame_start = Instant::now();
+        let dt = frame_start.duration_since(last_frame).as_secs_f32().min(MAX_FRAME_TIME);
+        last_frame = frame_start;
+        context.time = frame_start.duration_since(start).as_secs_f32();
+        context.profiler.begin_frame();
+
+        context.input.begin_frame();
+
+        let events: Vec<Event> = context.windsdl.event_pump.poll_iter().collect();
+        for event in events {
+            context.input.handle_event(&event);
+            app.on_event(&mut context, &event);
+        }
+
+        let fixed_timestep = context.settings.fixed_timestep;
+        accumulator += dt;
+
+        let mut steps = 0;
+        while accumulator >= fixed_timestep {
+            if steps == context.settings.max_fixed_steps {
+                // Drop the time we can't catch up with
+                accumulator %= fixed_timestep;
+                break;
+            }
+
+            app.fixed_update(&mut context, fixed_timestep);
+            accumulator -= fixed_timestep;
+            steps += 1;
+        }
+
+        app.update(&mut context, dt);
+        app.render(&mut context, accumulator / fixed_timestep);
+
+        context.profiler.end_frame();
+        context.windsdl.window.gl_swap_window();
+        context.frame += 1;
+
+        if let Some(frame_limit) = context.settings.frame_limit {
+            let frame_time = Duration::from_secs_f32(1.0 / frame_limit);
+            let elapsed = frame_start.elapsed();
+
+            if elapsed < frame_time {
+                std::thread::sleep(frame_time - elapsed);
+            }
+        }
+    }
+
+    app.shutdown(&mut context);
+    context.profiler.delete();
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use std::ffi::CString;
+
+use crate::graphics::animation::{AnimationClip, AnimationPlayer};
+use crate::graphics::camera::Camera;
+use crate::graphics::gltf_import::GltfScene;
+use crate::graphics::mesh::Mesh;
+use crate::graphics::program::Program;
+use crate::graphics::resources::Resources;
+use crate::graphics::scene::{NodeId, SceneGraph};
+use crate::graphics::shader::Shader;
+use crate::graphics::skin::{self, Skeleton, JOINTS_BINDING};
+use crate::graphics::ubo::UBO;
+
+/// Seconds the clips blend over when switching
+const CROSS_FADE: f32 = 0.5;
+
+/// Skinned glTF model playing its animations in a loop
+pub struct AnimatedModel {
+    program: Program,
+    meshes: Vec<Mesh>,
+    scene: SceneGraph,
+    // Scene node of each glTF node, the animation channels use the glTF indices
+    node_ids: Vec<NodeId>,
+    skeletons: Vec<Skeleton>,
+    joint_buffer: UBO,
+    clips: Vec<AnimationClip>,
+    player: AnimationPlayer,
+}
+
+impl AnimatedModel {
+    /// Starts playing the first animation of the file
+    pub fn from_res(gl: &gl::Gl, res: &Resources, name: &str) -> Result<AnimatedModel, String> {
+        let gltf = GltfScene::from_res(res, name).map_err(|e| e.to_string())?;
+        let scene = SceneGraph::from_gltf(&gltf, None)?;
+
+        let vert_shader = Shader::from_vert_source(
+            gl,
+            &CString::new(include_str!("../../assets/shaders/skinned.vert")).unwrap()
+        )?;
+        let frag_shader = Shader::from_frag_source(
+            gl,
+            &CString::new(include_str!("../../assets/shaders/triangle.frag")).unwrap()
+        )?;
+        vert_shader.set_label("skinned.vert");
+        frag_shader.set_label("triangle.frag");
+
+        let program = Program::from_shaders(gl, &[vert_shader, frag_shader])?;
+        program.set_label("skinned");
+        program.bind_uniform_block("Joints", JOINTS_BINDING);
+
+        let node_ids: Vec<NodeId> = (0..gltf.nodes.len()).map(|index| scene.node_at(index)).collect();
+        let skeletons = gltf.skins.iter()
+            .map(|skin| Skeleton::from_skin(skin, |joint| node_ids[joint]))
+            .collect();
+
+        let mut player = AnimationPlayer::new();
+        if !gltf.animations.is_empty() {
+            player.play(0, true);
+        }
+
+        Ok(AnimatedModel {
+            program,
+            meshes: gltf.upload_meshes(gl).into_iter().flatten().collect(),
+            scene,
+            node_ids,
+            skeletons,
+            joint_buffer: skin::joint_buffer(gl),
+            clips: gltf.animations,
+            player,
+        })
+    }
+
+    /// Fades to the next animation, returns its name
+    pub fn next_clip(&mut self) -> Option<&str> {
+        if self.clips.is_empty() {
+            return None;
+        }
+
+        let next = self.player.current_clip().map_or(0, |clip| (clip + 1) % self.clips.len());
+        self.player.cross_fade(next, true, CROSS_FADE);
+
+        Some(&self.clips[next].name)
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.player.update(dt);
+
+        let node_ids = &self.node_ids;
+        self.player.pose(&self.clips).apply(&mut self.scene, |index| node_ids[index]);
+    }
+
+    /// Uses the texture bound to unit 0
+    pub fn render(&mut self, camera: &Camera) {
+        self.program.set_used();
+        camera.matrix(&self.program, "camMatrix");
+
+        self.scene.render_skinned(&self.program, &self.meshes, &self.skeletons, &self.joint_buffer);
+    }
+
+    pub fn delete(&self) {
+        for mesh in &self.meshes {
+            mesh.delete();
+        }
+        self.joint_buffer.delete();
+    }
+}
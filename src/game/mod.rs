@@ -0,0 +1,2 @@
+pub mod animated_model;
+pub mod triangle;
@@ -0,0 +1,513 @@
+use std::ffi::CString;
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+
+use gl::types::GLsizei;
+use image::io::Reader as ImageReader;
+use image::{Rgba, RgbaImage};
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::mouse::SystemCursor;
+
+use crate::app::{App, AppContext};
+use crate::game::animated_model::AnimatedModel;
+use crate::gamepad::Gamepads;
+use crate::graphics::camera::{Camera, Projection};
+use crate::graphics::camera_controller::{CameraController, FpsController, FreeFlyController, OrbitController};
+use crate::graphics::camera_path::{CameraKeyframe, CameraPath, CameraPathPlayer, CameraTransition, PathInterpolation};
+use crate::graphics::mesh::{Mesh, MeshData};
+use crate::graphics::profiler::ProfilerOverlay;
+use crate::graphics::program::Program;
+use crate::graphics::render_state::{PolygonMode, RenderState};
+use crate::graphics::resources::Resources;
+use crate::graphics::scene::{NodeId, SceneGraph, Transform};
+use crate::graphics::shader::Shader;
+use crate::graphics::texture::Texture;
+use crate::input::InputMap;
+use crate::windsdl::{SharedContext, WindowPlacement};
+
+/// The loading thread gives its context back to drop it on the main thread
+type TextureLoader = JoinHandle<(SharedContext, Result<Texture, String>)>;
+
+/// The textured pyramid demo with its orbiting moon
+pub struct TriangleGame {
+    shader_program: Program,
+    meshes: Vec<Mesh>,
+    // Kept on the CPU for picking
+    mesh_data: Vec<MeshData>,
+    scene: SceneGraph,
+    pyramid_node: NodeId,
+    moon_node: NodeId,
+    // Next to the pyramid, N fades to its next animation
+    animated_model: AnimatedModel,
+    // Plain white until `texture_loader` is done
+    texture: Texture,
+    texture_loader: Option<TextureLoader>,
+    camera: Camera,
+    controllers: Vec<Box<dyn CameraController>>,
+    active_controller: usize,
+    // Both take over the camera from the controller while they run
+    camera_tour: CameraPathPlayer,
+    camera_transition: Option<CameraTransition>,
+    gamepads: Gamepads,
+    // None if there is no per-user directory to save it in
+    placement_path: Option<PathBuf>,
+    profiler_overlay: ProfilerOverlay,
+    show_profiler: bool,
+    wireframe: bool,
+    // Time the window title was last updated with the frame statistics
+    last_title_update: f32,
+    // Spin angle of the pyramid at the last two fixed updates, interpolated when rendering
+    previous_spin: f32,
+    spin: f32,
+}
+
+impl App for TriangleGame {
+    fn init(context: &mut AppContext) -> Result<TriangleGame, String> {
+        let res = Resources::from_relative_exe_path(Path::new("assets-07"))
+            .map_err(|e| format!("{:?}", e))?;
+        let windsdl = &mut context.windsdl;
+        let gl = &context.gl;
+
+        println!("{}", windsdl.context_info);
+
+        for display in windsdl.displays().unwrap_or_default() {
+            println!(
+                "Display {}: {} {}x{} @ {}Hz, {} modes",
+                display.index, display.name, display.desktop_mode.w, display.desktop_mode.h,
+                display.desktop_mode.refresh_rate, display.modes.len()
+            );
+        }
+
+        // Window placement of the last run, in the user's directory as the assets may be read-only
+        let placement_path = match sdl2::filesystem::pref_path("RustOpenGLSDL2", "triangle") {
+            Ok(directory) => Some(PathBuf::from(directory).join("window.cfg")),
+            Err(error) => {
+                println!("No directory to save the window placement in: {}", error);
+                None
+            }
+        };
+        if let Some(Ok(placement)) = placement_path.as_deref().map(WindowPlacement::load) {
+            if let Err(error) = windsdl.restore_placement(&placement) {
+                println!("Failed to restore the window placement: {}", error);
+            }
+        }
+
+        // Load Shaders
+        let vert_shader = Shader::from_vert_source(
+            gl,
+            &CString::new(include_str!("../../assets/shaders/triangle.vert")).unwrap()
+        )?;
+
+        let frag_shader = Shader::from_frag_source(
+            gl,
+            &CString::new(include_str!("../../assets/shaders/triangle.frag")).unwrap()
+        )?;
+
+        vert_shader.set_label("triangle.vert");
+        frag_shader.set_label("triangle.frag");
+
+        let shader_program = Program::from_shaders(
+            gl,
+            &[vert_shader, frag_shader]
+        )?;
+        shader_program.set_label("triangle");
+
+
+        // Pyramid
+        let mut pyramid = MeshData::new();
+        //                  positions          normals (generated)  Texture
+        pyramid.push_vertex([-0.5, 0.0,  0.5], [0.0, 0.0, 0.0], [0.0, 0.0]);
+        pyramid.push_vertex([-0.5, 0.0, -0.5], [0.0, 0.0, 0.0], [5.0, 0.0]);
+        pyramid.push_vertex([ 0.5, 0.0, -0.5], [0.0, 0.0, 0.0], [0.0, 0.0]);
+        pyramid.push_vertex([ 0.5, 0.0,  0.5], [0.0, 0.0, 0.0], [5.0, 0.0]);
+        pyramid.push_vertex([ 0.0, 0.8,  0.0], [0.0, 0.0, 0.0], [2.5, 5.0]);
+
+        pyramid.indices = vec![
+            0, 1, 2,
+            0, 2, 3,
+            0, 1, 4,
+            1, 2, 4,
+            2, 3, 4,
+            3, 0, 4
+        ];  // define the triangle indices
+
+        pyramid.generate_normals(&vec![true; pyramid.vertex_count()]);
+
+        let meshes = vec![Mesh::new(gl, &pyramid)];
+        meshes[0].set_label("pyramid");
+        let mesh_data = vec![pyramid];
+
+        // Scene
+        let mut scene = SceneGraph::new();
+
+        let pyramid_node = scene.add_mesh_node("pyramid", Transform::identity(), None, 0);
+        let moon_node = scene.add_mesh_node(
+            "moon",
+            Transform::new(Vector3::new(1.5, 0.0, 0.0), UnitQuaternion::identity(), Vector3::new(0.4, 0.4, 0.4)),
+            Some(pyramid_node),
+            0
+        );
+
+        let animated_model = AnimatedModel::from_res(gl, &res, "models/bender.gltf")?;
+
+        // Texture, decoded and uploaded on another thread while the first frames are drawn
+        let texture = Texture::new(gl, &RgbaImage::from_pixel(1, 1, Rgba([255, 255, 255, 255])), gl::TEXTURE_2D);
+        texture.set_label("placeholder");
+
+        texture.tex_uniform(String::from("tex0"), &shader_program, 0);
+
+        let texture_loader = load_texture(windsdl.create_shared_context()?, PathBuf::from("./assets/textures/pop_cat.png"));
+
+
+        // The drawable size differs from the window size on HiDPI screens
+        let drawable_size = windsdl.drawable_size();
+
+        unsafe {
+            // Background color
+            gl.Viewport(0, 0, drawable_size.width as GLsizei, drawable_size.height as GLsizei);
+            gl.ClearColor(0.3, 0.3, 0.5, 1.0);
+        }
+
+        // Input bindings, the defaults are used if the config can't be loaded
+        context.input.map = InputMap::from_res(&res, "input.cfg").unwrap_or_else(|error| {
+            println!("Using the default input bindings ({})", error);
+            InputMap::defaults()
+        });
+
+        // Controllers, gamecontrollerdb.txt adds mappings for the ones SDL doesn't know
+        let gamepads = Gamepads::new(&windsdl.game_controller_subsystem);
+        let mappings_path = res.path("gamecontrollerdb.txt");
+        if mappings_path.exists() {
+            match gamepads.load_mappings(&mappings_path) {
+                Ok(count) => println!("Loaded {} controller mappings", count),
+                Err(error) => println!("Failed to load the controller mappings: {}", error),
+            }
+        }
+
+        // Shown while the mouse is released, to pick under the cursor
+        windsdl.set_system_cursor(SystemCursor::Crosshair)?;
+
+        // Camera shit
+        let mut camera: Camera = Camera::new(gl, drawable_size.width as i32, drawable_size.height as i32, Point3::new(0.0, 0.0, 2.0));
+
+        // First person, orbit around the pyramid and free fly (1, 2, 3 by default)
+        let mut controllers: Vec<Box<dyn CameraController>> = vec![
+            Box::new(FpsController::new()),
+            Box::new(OrbitController::new(Point3::new(0.0, 0.4, 0.0))),
+            Box::new(FreeFlyController::new()),
+        ];
+        controllers[0].activate(&mut camera);
+
+        camera.projection.configure_depth(gl)?;
+
+        // Loop around the pyramid (T by default)
+        let pyramid_center = Point3::new(0.0, 0.4, 0.0);
+        let mut tour_path = CameraPath::new(PathInterpolation::CatmullRom);
+        for (i, position) in [(0.0, 0.8, 2.5), (2.5, 1.5, 0.0), (0.0, 0.8, -2.5), (-2.5, 1.5, 0.0), (0.0, 0.8, 2.5)].iter().enumerate() {
+            let position = Point3::new(position.0, position.1, position.2);
+            tour_path.add_keyframe(CameraKeyframe::new(i as f32 * 3.0, position, pyramid_center, 45.0));
+        }
+        let mut camera_tour = CameraPathPlayer::new(tour_path);
+        camera_tour.looping = true;
+
+        Ok(TriangleGame {
+            shader_program,
+            meshes,
+            mesh_data,
+            scene,
+            pyramid_node,
+            moon_node,
+            animated_model,
+            texture,
+            texture_loader: Some(texture_loader),
+            camera,
+            controllers,
+            active_controller: 0,
+            camera_tour,
+            camera_transition: None,
+            gamepads,
+            placement_path,
+            profiler_overlay: ProfilerOverlay::new(gl)?,
+            show_profiler: false,
+            wireframe: false,
+            last_title_update: 0.0,
+            previous_spin: 0.0,
+            spin: 0.0,
+        })
+    }
+
+    fn on_event(&mut self, context: &mut AppContext, event: &Event) {
+        context.windsdl.handle_resize(&context.gl, event, &mut [&mut self.camera]);
+        self.gamepads.handle_event(event);
+
+        match event {
+            Event::Quit { .. } => context.quit(),
+
+            // Give the mouse back when switching to another window
+            Event::Window { win_event: WindowEvent::FocusLost, .. } => {
+                context.windsdl.set_mouse_captured(false);
+                context.input.reset();
+            }
+
+            _ => { }
+        }
+    }
+
+    fn fixed_update(&mut self, _context: &mut AppContext, dt: f32) {
+        self.previous_spin = self.spin;
+        self.spin += dt;
+    }
+
+    fn update(&mut self, context: &mut AppContext, dt: f32) {
+        if self.texture_loader.as_ref().is_some_and(|loader| loader.is_finished()) {
+            self.finish_texture_loading(&context.gl);
+        }
+
+        let windsdl = &mut context.windsdl;
+        let input = &mut context.input;
+
+        if input.just_pressed("cycle_window_mode") {
+            if let Err(error) = windsdl.cycle_window_mode() {
+                println!("Failed to change the window mode: {}", error);
+            }
+        }
+
+        // Send the window to the next screen, e.g. a projector
+        if input.just_pressed("next_display") {
+            let displays = windsdl.video_subsystem.num_video_displays().unwrap_or(1).max(1);
+            let next = (windsdl.current_display().unwrap_or(0) + 1) % displays;
+
+            if let Err(error) = windsdl.move_to_display(next) {
+                println!("Failed to move the window: {}", error);
+            }
+        }
+
+        if input.just_pressed("toggle_mouse_capture") {
+            windsdl.toggle_mouse_capture();
+        }
+        input.mouse_look = windsdl.is_mouse_captured();
+
+        if input.just_pressed("quit") {
+            context.quit();
+            return;
+        }
+
+        if input.just_pressed("toggle_profiler") {
+            self.show_profiler = !self.show_profiler;
+        }
+
+        if input.just_pressed("toggle_wireframe") {
+            self.wireframe = !self.wireframe;
+        }
+
+        if input.just_pressed("next_animation") {
+            if let Some(name) = self.animated_model.next_clip() {
+                println!("Playing {}", name);
+            }
+        }
+        self.animated_model.update(dt);
+
+        if input.just_pressed("export_profile") {
+            match context.profiler.export_csv(Path::new("profile.csv")) {
+                Ok(()) => println!("Frame times exported to profile.csv"),
+                Err(error) => println!("Failed to export the frame times: {}", error),
+            }
+        }
+
+        // Frame statistics in the title, once per second
+        if context.time - self.last_title_update >= 1.0 {
+            self.last_title_update = context.time;
+
+            let frame = context.profiler.frame_stats();
+            let gpu = context.profiler.gpu_stats();
+            let (draw_calls, triangles, state_calls, skipped) = context.profiler.latest()
+                .map_or((0, 0, 0, 0), |sample| (
+                    sample.draw_calls, sample.triangles, sample.state_calls_issued, sample.state_calls_skipped
+                ));
+
+            let title = format!(
+                "My Window - {:.0} fps, {:.2} ms (p95 {:.2}, p99 {:.2}), GPU {:.2} ms, {} draws, {} triangles, {} state calls ({} skipped)",
+                1000.0 / frame.average.max(0.001), frame.average, frame.p95, frame.p99, gpu.average,
+                draw_calls, triangles, state_calls, skipped
+            );
+            windsdl.window.set_title(&title).ok();
+        }
+
+        let camera = &mut self.camera;
+
+        // Pick what is under the crosshair, or under the cursor when the mouse is released
+        if input.just_pressed("pick") {
+            let (x, y) = if windsdl.is_mouse_captured() {
+                (camera.width as f32 / 2.0, camera.height as f32 / 2.0)
+            } else {
+                let (x, y) = input.mouse_position();
+                windsdl.window_to_drawable(x, y)
+            };
+            let ray = camera.screen_ray(x, y);
+
+            match self.scene.pick(&ray, &self.mesh_data) {
+                Some((node, distance)) => println!("Picked {} at {:.2}", self.scene.node(node).name, distance),
+                None => println!("Picked nothing"),
+            }
+        }
+
+        if input.just_pressed("cycle_projection") {
+            let mut projection = camera.projection;
+            loop {
+                projection = match projection {
+                    Projection::Perspective { .. } => Projection::Orthographic { height: 3.0, near: 0.1, far: 100.0 },
+                    Projection::Orthographic { .. } => Projection::ReverseZInfinite { fov_deg: 45.0, near: 0.1 },
+                    Projection::ReverseZInfinite { .. } => Projection::default(),
+                };
+
+                // Skip what the context can't do, e.g. reversed depth before GL 4.5
+                match projection.configure_depth(&context.gl) {
+                    Ok(()) => break,
+                    Err(error) => println!("Skipping {:?}: {}", projection, error),
+                }
+            }
+            camera.projection = projection;
+        }
+
+        let selected_controller = ["fps_camera", "orbit_camera", "free_camera"].iter()
+            .position(|action| input.just_pressed(action));
+
+        if let Some(selected_controller) = selected_controller {
+            self.camera_tour.stop();
+            self.camera_transition = None;
+            self.active_controller = selected_controller;
+            self.controllers[self.active_controller].activate(camera);
+        }
+
+        if input.just_pressed("camera_tour") {
+            self.camera_transition = None;
+            if self.camera_tour.is_playing() {
+                self.camera_tour.stop();
+                self.controllers[self.active_controller].activate(camera);
+            } else {
+                self.camera_tour.play();
+            }
+        }
+
+        // Back to the starting view
+        if input.just_pressed("camera_home") {
+            self.camera_tour.stop();
+            let home = CameraKeyframe::new(0.0, Point3::new(0.0, 0.4, 2.0), Point3::new(0.0, 0.4, 0.0), 45.0);
+            self.camera_transition = Some(CameraTransition::fly_to(camera, home, 1.0));
+        }
+
+        if self.camera_tour.is_playing() {
+            self.camera_tour.update(camera, dt);
+        } else if let Some(transition) = &mut self.camera_transition {
+            transition.update(camera, dt);
+
+            if transition.is_finished() {
+                self.camera_transition = None;
+                // Continue from where it stopped
+                self.controllers[self.active_controller].activate(camera);
+            }
+        } else {
+            self.controllers[self.active_controller].update(camera, input, dt);
+        }
+    }
+
+    fn render(&mut self, context: &mut AppContext, alpha: f32) {
+        let gl = &context.gl;
+
+        // Before clearing, the depth write mask applies to glClear too
+        let polygon_mode = if self.wireframe { PolygonMode::Line } else { PolygonMode::Fill };
+        RenderState::opaque()
+            .depth_func(self.camera.projection.depth_func())
+            .polygon_mode(polygon_mode)
+            .apply(gl);
+
+        unsafe {
+            gl.Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            self.shader_program.set_used();
+
+            // Camera update
+            self.camera.matrix(&self.shader_program, "camMatrix");
+
+            // Texture
+            self.texture.bind();
+        }
+
+        // Spin the pyramid, the moon follows it and spins on its own
+        let spin = self.previous_spin + (self.spin - self.previous_spin) * alpha;
+        self.scene.update_transform(self.pyramid_node, |transform| {
+            transform.isometry.rotation = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), spin);
+        });
+        self.scene.update_transform(self.moon_node, |transform| {
+            transform.isometry.rotation = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), spin * 2.0);
+        });
+
+        self.scene.render_visible(&self.shader_program, &self.meshes, &self.camera.frustum());
+        self.animated_model.render(&self.camera);
+
+        if self.show_profiler {
+            self.profiler_overlay.draw(&context.profiler);
+        }
+    }
+
+    fn shutdown(&mut self, context: &mut AppContext) {
+        if let Some(placement_path) = &self.placement_path {
+            if let Err(error) = context.windsdl.placement().save(placement_path) {
+                println!("Failed to save the window placement: {}", error);
+            }
+        }
+
+        for mesh in &self.meshes {
+            mesh.delete();
+        }
+        self.animated_model.delete();
+        // The window's context has to outlive the loading one
+        self.finish_texture_loading(&context.gl);
+        self.texture.delete();
+        self.profiler_overlay.delete();
+    }
+}
+
+impl TriangleGame {
+    /// Swaps the placeholder for the loaded texture, waits for the loading thread if it is still running
+    fn finish_texture_loading(&mut self, gl: &gl::Gl) {
+        let loader = match self.texture_loader.take() {
+            Some(loader) => loader,
+            None => return,
+        };
+
+        match loader.join() {
+            Ok((_loading_context, Ok(mut texture))) => {
+                texture.adopt(gl);
+                self.texture.delete();
+                self.texture = texture;
+            }
+            Ok((_loading_context, Err(error))) => println!("Failed to load the texture: {}", error),
+            Err(_) => println!("The texture loading thread panicked"),
+        }
+    }
+}
+
+/// Decodes the image and uploads it with `shared_context` on a new thread
+fn load_texture(shared_context: SharedContext, path: PathBuf) -> TextureLoader {
+    thread::spawn(move || {
+        let load = || -> Result<Texture, String> {
+            let img = ImageReader::open(&path)
+                .map_err(|e| format!("{}: {}", path.display(), e))?
+                .decode()
+                .map_err(|e| format!("{}: {}", path.display(), e))?
+                .flipv()
+                .to_rgba8();
+
+            let current = shared_context.make_current()?;
+            let texture = Texture::new(&shared_context.gl, &img, gl::TEXTURE_2D);
+            texture.set_label("pop_cat.png");
+            current.finish();
+
+            Ok(texture)
+        };
+
+        let texture = load();
+        (shared_context, texture)
+    })
+}
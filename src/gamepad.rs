@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use sdl2::GameControllerSubsystem;
+use sdl2::controller::GameController;
+use sdl2::event::Event;
+
+/// Game controllers currently plugged in
+/// SDL sends a `ControllerDeviceAdded` event for each controller already connected at startup,
+/// so every controller goes through `handle_event`
+pub struct Gamepads {
+    subsystem : GameControllerSubsystem,
+    // SDL stops reporting a controller's events once it is dropped
+    controllers : HashMap<u32, GameController>,
+}
+
+impl Gamepads {
+    pub fn new(subsystem : &GameControllerSubsystem) -> Gamepads {
+        Gamepads {
+            subsystem : subsystem.clone(),
+            controllers : HashMap::new(),
+        }
+    }
+
+    /// Adds the mappings of a gamecontrollerdb.txt file (https://github.com/gabomdq/SDL_GameControllerDB)
+    /// Returns how many were added, they only apply to controllers plugged in afterwards
+    pub fn load_mappings(&self, path : &Path) -> Result<i32, String> {
+        self.subsystem.load_mappings(path).map_err(|error| error.to_string())
+    }
+
+    /// Opens and closes the controllers as they are plugged and unplugged
+    pub fn handle_event(&mut self, event : &Event) {
+        match event {
+            // `which` is a device index here...
+            Event::ControllerDeviceAdded { which, .. } => {
+                match self.subsystem.open(*which) {
+                    Ok(controller) => {
+                        println!("Controller connected: {}", controller.name());
+                        self.controllers.insert(controller.instance_id(), controller);
+                    }
+                    Err(error) => println!("Failed to open controller {}: {}", which, error),
+                }
+            }
+            // ...and an instance id here
+            Event::ControllerDeviceRemoved { which, .. } => {
+                if let Some(controller) = self.controllers.remove(which) {
+                    println!("Controller disconnected: {}", controller.name());
+                }
+            }
+            // Joysticks SDL has no mapping for never become game controllers
+            Event::JoyDeviceAdded { which, .. } if !self.subsystem.is_game_controller(*which) => {
+                println!("Joystick {} has no game controller mapping, add it to gamecontrollerdb.txt", which);
+            }
+            _ => { }
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.controllers.len()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.controllers.values().map(GameController::name).collect()
+    }
+}
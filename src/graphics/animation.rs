@@ -0,0 +1,509 @@
+use std::collections::HashMap;
+
+use nalgebra::{Quaternion, UnitQuaternion, Vector3};
+
+use crate::graphics::scene::{NodeId, SceneGraph};
+
+/// How the values between two keyframes are computed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    Linear,
+    Step,
+    /// Every keyframe stores an in-tangent, the value and an out-tangent
+    CubicSpline,
+}
+
+/// Node property driven by a channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Property {
+    Translation,
+    Rotation,
+    Scale,
+    /// Weights of every morph target of the node's mesh
+    MorphWeights(usize),
+}
+
+impl Property {
+    /// Number of floats of a single value of the property
+    pub fn components(&self) -> usize {
+        match self {
+            Property::Translation | Property::Scale => 3,
+            Property::Rotation => 4,
+            Property::MorphWeights(count) => *count,
+        }
+    }
+}
+
+/// Keyframes of one property of one node
+#[derive(Debug, Clone)]
+pub struct Channel {
+    pub node: usize,
+    pub property: Property,
+    pub interpolation: Interpolation,
+    pub times: Vec<f32>,
+    /// Flattened values, `property.components()` floats per value
+    /// (three values per keyframe for cubic splines)
+    pub values: Vec<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationClip {
+    pub name: String,
+    pub channels: Vec<Channel>,
+    pub duration: f32,
+}
+
+impl AnimationClip {
+    pub fn new(name: &str, channels: Vec<Channel>) -> AnimationClip {
+        let duration = channels.iter()
+            .filter_map(|channel| channel.times.last().copied())
+            .fold(0.0f32, f32::max);
+
+        AnimationClip {
+            name: String::from(name),
+            channels,
+            duration,
+        }
+    }
+
+    /// Samples every channel at `time`, wrapping around when `looping`
+    pub fn sample(&self, time: f32, looping: bool) -> Pose {
+        let time = if looping && self.duration > 0.0 {
+            time.rem_euclid(self.duration)
+        } else {
+            time.clamp(0.0, self.duration)
+        };
+
+        let mut pose = Pose::new();
+
+        for channel in &self.channels {
+            let values = channel.sample(time);
+            let node = pose.nodes.entry(channel.node).or_default();
+
+            match channel.property {
+                Property::Translation => node.translation = Some(Vector3::new(values[0], values[1], values[2])),
+                Property::Scale => node.scale = Some(Vector3::new(values[0], values[1], values[2])),
+                Property::Rotation => node.rotation = Some(UnitQuaternion::from_quaternion(
+                    Quaternion::new(values[3], values[0], values[1], values[2])
+                )),
+                Property::MorphWeights(_) => node.weights = Some(values),
+            }
+        }
+
+        pose
+    }
+}
+
+impl Channel {
+    /// Interpolated value at `time`, clamped to the first and last keyframes
+    pub fn sample(&self, time: f32) -> Vec<f32> {
+        let components = self.property.components();
+
+        // Cubic splines store in-tangent, value, out-tangent for every keyframe
+        let value = |key: usize| -> &[f32] {
+            match self.interpolation {
+                Interpolation::CubicSpline => &self.values[(key * 3 + 1) * components..(key * 3 + 2) * components],
+                _ => &self.values[key * components..(key + 1) * components],
+            }
+        };
+
+        if self.times.is_empty() {
+            return vec![0.0; components];
+        }
+
+        let last = self.times.len() - 1;
+        // NaN (e.g. from an empty clip) compares false to everything, use the first keyframe
+        if time.is_nan() || time <= self.times[0] {
+            return value(0).to_vec();
+        }
+        if time >= self.times[last] {
+            return value(last).to_vec();
+        }
+
+        // Index of the keyframe right before `time`
+        let key = match self.times.binary_search_by(|probe| probe.total_cmp(&time)) {
+            Ok(index) => return value(index).to_vec(),
+            Err(index) => index - 1,
+        };
+
+        let delta = self.times[key + 1] - self.times[key];
+        let t = (time - self.times[key]) / delta;
+
+        let mut result = match self.interpolation {
+            Interpolation::Step => value(key).to_vec(),
+            Interpolation::Linear => {
+                if self.property == Property::Rotation {
+                    return slerp(value(key), value(key + 1), t);
+                }
+
+                value(key).iter().zip(value(key + 1))
+                    .map(|(a, b)| a + (b - a) * t)
+                    .collect()
+            }
+            Interpolation::CubicSpline => {
+                let out_tangent = &self.values[(key * 3 + 2) * components..(key * 3 + 3) * components];
+                let in_tangent = &self.values[((key + 1) * 3) * components..((key + 1) * 3 + 1) * components];
+
+                let t2 = t * t;
+                let t3 = t2 * t;
+
+                (0..components)
+                    .map(|i| {
+                        (2.0 * t3 - 3.0 * t2 + 1.0) * value(key)[i]
+                            + (t3 - 2.0 * t2 + t) * delta * out_tangent[i]
+                            + (-2.0 * t3 + 3.0 * t2) * value(key + 1)[i]
+                            + (t3 - t2) * delta * in_tangent[i]
+                    })
+                    .collect()
+            }
+        };
+
+        if self.property == Property::Rotation {
+            normalize(&mut result);
+        }
+
+        result
+    }
+}
+
+/// Spherical interpolation of two x, y, z, w quaternions through the shortest path
+fn slerp(a: &[f32], b: &[f32], t: f32) -> Vec<f32> {
+    let a = UnitQuaternion::from_quaternion(Quaternion::new(a[3], a[0], a[1], a[2]));
+    let mut b = Quaternion::new(b[3], b[0], b[1], b[2]);
+
+    if a.coords.dot(&b.coords) < 0.0 {
+        b = -b;
+    }
+
+    let result = a.try_slerp(&UnitQuaternion::from_quaternion(b), t, 1.0e-6)
+        .unwrap_or_else(|| a.nlerp(&UnitQuaternion::from_quaternion(b), t));
+
+    vec![result.i, result.j, result.k, result.w]
+}
+
+fn normalize(values: &mut [f32]) {
+    let length = values.iter().map(|value| value * value).sum::<f32>().sqrt();
+
+    if length > 0.0 {
+        for value in values.iter_mut() {
+            *value /= length;
+        }
+    }
+}
+
+/// Animated properties of a single node, `None` keeps the node's own value
+#[derive(Debug, Clone, Default)]
+pub struct NodePose {
+    pub translation: Option<Vector3<f32>>,
+    pub rotation: Option<UnitQuaternion<f32>>,
+    pub scale: Option<Vector3<f32>>,
+    pub weights: Option<Vec<f32>>,
+}
+
+/// Result of sampling or blending clips, keyed by the channel node index
+#[derive(Debug, Clone, Default)]
+pub struct Pose {
+    pub nodes: HashMap<usize, NodePose>,
+}
+
+impl Pose {
+    pub fn new() -> Pose {
+        Pose::default()
+    }
+
+    /// Blends towards `other` by `factor` (0 keeps `self`, 1 gives `other`)
+    /// Properties animated by only one of the poses are taken as is
+    pub fn blend(&self, other: &Pose, factor: f32) -> Pose {
+        let factor = factor.clamp(0.0, 1.0);
+        let mut result = self.clone();
+
+        for (node, pose) in &other.nodes {
+            let target = result.nodes.entry(*node).or_default();
+
+            target.translation = match (target.translation, pose.translation) {
+                (Some(a), Some(b)) => Some(a.lerp(&b, factor)),
+                (a, b) => a.or(b),
+            };
+            target.scale = match (target.scale, pose.scale) {
+                (Some(a), Some(b)) => Some(a.lerp(&b, factor)),
+                (a, b) => a.or(b),
+            };
+            target.weights = match (target.weights.take(), &pose.weights) {
+                (Some(a), Some(b)) => Some(a.iter().zip(b).map(|(a, b)| a + (b - a) * factor).collect()),
+                (a, b) => a.or_else(|| b.clone()),
+            };
+            target.rotation = match (target.rotation, pose.rotation) {
+                (Some(a), Some(b)) => {
+                    let b = if a.coords.dot(&b.coords) < 0.0 {
+                        UnitQuaternion::from_quaternion(-b.into_inner())
+                    } else {
+                        b
+                    };
+                    Some(a.try_slerp(&b, factor, 1.0e-6).unwrap_or_else(|| a.nlerp(&b, factor)))
+                }
+                (a, b) => a.or(b),
+            };
+        }
+
+        result
+    }
+
+    /// Writes the pose into the scene, `node_id` maps channel node indices to scene nodes
+    pub fn apply<F: Fn(usize) -> NodeId>(&self, scene: &mut SceneGraph, node_id: F) {
+        for (node, pose) in &self.nodes {
+            if let Some(weights) = &pose.weights {
+                scene.set_morph_weights(node_id(*node), weights);
+            }
+
+            scene.update_transform(node_id(*node), |transform| {
+                if let Some(translation) = pose.translation {
+                    transform.isometry.translation.vector = translation;
+                }
+                if let Some(rotation) = pose.rotation {
+                    transform.isometry.rotation = rotation;
+                }
+                if let Some(scale) = pose.scale {
+                    transform.scale = scale;
+                }
+            });
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PlayingClip {
+    clip: usize,
+    time: f32,
+    looping: bool,
+}
+
+/// Plays clips over time and cross-fades between them
+#[derive(Debug, Clone)]
+pub struct AnimationPlayer {
+    current: Option<PlayingClip>,
+    previous: Option<PlayingClip>,
+    fade_time: f32,
+    fade_duration: f32,
+    pub speed: f32,
+}
+
+impl Default for AnimationPlayer {
+    fn default() -> AnimationPlayer {
+        AnimationPlayer::new()
+    }
+}
+
+impl AnimationPlayer {
+    pub fn new() -> AnimationPlayer {
+        AnimationPlayer {
+            current: None,
+            previous: None,
+            fade_time: 0.0,
+            fade_duration: 0.0,
+            speed: 1.0,
+        }
+    }
+
+    /// Starts `clip` right away, dropping any running fade
+    pub fn play(&mut self, clip: usize, looping: bool) {
+        self.current = Some(PlayingClip { clip, time: 0.0, looping });
+        self.previous = None;
+        self.fade_duration = 0.0;
+    }
+
+    /// Starts `clip` and fades out the current one over `duration` seconds
+    pub fn cross_fade(&mut self, clip: usize, looping: bool, duration: f32) {
+        if duration <= 0.0 || self.current.is_none() {
+            return self.play(clip, looping);
+        }
+
+        self.previous = self.current;
+        self.current = Some(PlayingClip { clip, time: 0.0, looping });
+        self.fade_time = 0.0;
+        self.fade_duration = duration;
+    }
+
+    pub fn stop(&mut self) {
+        self.current = None;
+        self.previous = None;
+    }
+
+    pub fn current_clip(&self) -> Option<usize> {
+        self.current.map(|playing| playing.clip)
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let dt = dt * self.speed;
+
+        if let Some(current) = &mut self.current {
+            current.time += dt;
+        }
+
+        if let Some(previous) = &mut self.previous {
+            previous.time += dt;
+            self.fade_time += dt;
+
+            if self.fade_time >= self.fade_duration {
+                self.previous = None;
+            }
+        }
+    }
+
+    pub fn pose(&self, clips: &[AnimationClip]) -> Pose {
+        let current = match self.current {
+            Some(current) => clips[current.clip].sample(current.time, current.looping),
+            None => return Pose::new(),
+        };
+
+        match self.previous {
+            Some(previous) => {
+                let previous_pose = clips[previous.clip].sample(previous.time, previous.looping);
+                previous_pose.blend(&current, self.fade_time / self.fade_duration)
+            }
+            None => current,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{UnitQuaternion, Vector3};
+
+    use super::{AnimationClip, AnimationPlayer, Channel, Interpolation, NodePose, Pose, Property};
+
+    fn translation(interpolation: Interpolation, times: &[f32], values: &[f32]) -> Channel {
+        Channel {
+            node: 0,
+            property: Property::Translation,
+            interpolation,
+            times: times.to_vec(),
+            values: values.to_vec(),
+        }
+    }
+
+    /// Moves node 0 along x from `from` to `to` in one second
+    fn clip(name: &str, from: f32, to: f32) -> AnimationClip {
+        AnimationClip::new(name, vec![
+            translation(Interpolation::Linear, &[0.0, 1.0], &[from, 0.0, 0.0, to, 0.0, 0.0]),
+        ])
+    }
+
+    fn x(pose: &Pose) -> f32 {
+        pose.nodes[&0].translation.unwrap().x
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1.0e-5, "{} is not {}", a, b);
+    }
+
+    #[test]
+    fn step_holds_the_previous_key() {
+        let channel = translation(Interpolation::Step, &[0.0, 1.0], &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        assert_eq!(channel.sample(0.99), vec![1.0, 2.0, 3.0]);
+        assert_eq!(channel.sample(1.0), vec![4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn linear_interpolates_and_clamps() {
+        let channel = translation(Interpolation::Linear, &[1.0, 3.0], &[0.0, 0.0, 0.0, 4.0, 2.0, -2.0]);
+
+        assert_eq!(channel.sample(2.0), vec![2.0, 1.0, -1.0]);
+        assert_eq!(channel.sample(0.0), vec![0.0, 0.0, 0.0]);
+        assert_eq!(channel.sample(5.0), vec![4.0, 2.0, -2.0]);
+        assert_eq!(channel.sample(f32::NAN), vec![0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn cubic_spline_uses_the_tangents() {
+        // in-tangent, value, out-tangent for each key, only x moves
+        let flat = translation(Interpolation::CubicSpline, &[0.0, 1.0], &[
+            0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+        ]);
+        let straight = translation(Interpolation::CubicSpline, &[0.0, 1.0], &[
+            1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0,
+            1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0,
+        ]);
+
+        // Zero tangents ease in: 3t² - 2t³
+        assert_close(flat.sample(0.25)[0], 0.15625);
+        assert_close(flat.sample(0.5)[0], 0.5);
+        // Tangents matching the slope give a straight line
+        assert_close(straight.sample(0.25)[0], 0.25);
+        assert_eq!(straight.sample(1.0), vec![1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn rotations_take_the_shortest_path() {
+        let quarter_turn = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 90.0f32.to_radians());
+        // The same rotation with the opposite sign
+        let end = -quarter_turn.into_inner();
+        let channel = Channel {
+            node: 0,
+            property: Property::Rotation,
+            interpolation: Interpolation::Linear,
+            times: vec![0.0, 1.0],
+            values: vec![0.0, 0.0, 0.0, 1.0, end.i, end.j, end.k, end.w],
+        };
+
+        let clip = AnimationClip::new("turn", vec![channel]);
+        let rotation = clip.sample(0.5, false).nodes[&0].rotation.unwrap();
+
+        assert_close(rotation.angle(), 45.0f32.to_radians());
+        assert_close(rotation.axis().unwrap().z, 1.0);
+    }
+
+    #[test]
+    fn looping_wraps_the_time() {
+        let clip = clip("move", 0.0, 4.0);
+
+        assert_eq!(clip.duration, 1.0);
+        assert_close(x(&clip.sample(2.25, true)), 1.0);
+        assert_close(x(&clip.sample(-0.25, true)), 3.0);
+        assert_close(x(&clip.sample(2.25, false)), 4.0);
+    }
+
+    #[test]
+    fn blending_mixes_shared_properties() {
+        let mut a = Pose::new();
+        a.nodes.insert(0, NodePose { translation: Some(Vector3::new(0.0, 0.0, 0.0)), ..NodePose::default() });
+        let mut b = Pose::new();
+        b.nodes.insert(0, NodePose {
+            translation: Some(Vector3::new(4.0, 0.0, 0.0)),
+            scale: Some(Vector3::new(2.0, 2.0, 2.0)),
+            ..NodePose::default()
+        });
+        b.nodes.insert(1, NodePose { weights: Some(vec![0.5]), ..NodePose::default() });
+
+        let blended = a.blend(&b, 0.25);
+
+        assert_close(x(&blended), 1.0);
+        // Only animated by one pose
+        assert_eq!(blended.nodes[&0].scale, Some(Vector3::new(2.0, 2.0, 2.0)));
+        assert_eq!(blended.nodes[&1].weights, Some(vec![0.5]));
+        assert_close(x(&a.blend(&b, 2.0)), 4.0);
+    }
+
+    #[test]
+    fn cross_fade_blends_then_drops_the_previous_clip() {
+        let clips = [clip("left", 0.0, 0.0), clip("right", 8.0, 8.0)];
+        let mut player = AnimationPlayer::new();
+
+        player.play(0, true);
+        player.update(0.5);
+        player.cross_fade(1, true, 2.0);
+        assert_close(x(&player.pose(&clips)), 0.0);
+
+        player.update(0.5);
+        assert_eq!(player.current_clip(), Some(1));
+        assert_close(x(&player.pose(&clips)), 2.0);
+
+        player.update(2.0);
+        assert!(player.previous.is_none());
+        assert_close(x(&player.pose(&clips)), 8.0);
+
+        player.stop();
+        assert!(player.pose(&clips).nodes.is_empty());
+    }
+}
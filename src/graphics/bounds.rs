@@ -0,0 +1,88 @@
+use nalgebra::{Matrix4, Point3, Vector3};
+
+/// Axis aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Point3<f32>, max: Point3<f32>) -> Aabb {
+        Aabb { min, max }
+    }
+
+    /// Smallest box containing every point, `None` if there are no points
+    pub fn from_points<I: IntoIterator<Item = Point3<f32>>>(points: I) -> Option<Aabb> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+
+        let mut aabb = Aabb::new(first, first);
+        for point in points {
+            aabb.min = aabb.min.inf(&point);
+            aabb.max = aabb.max.sup(&point);
+        }
+
+        Some(aabb)
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        nalgebra::center(&self.min, &self.max)
+    }
+
+    /// Half of the size on each axis
+    pub fn half_extents(&self) -> Vector3<f32> {
+        (self.max - self.min) / 2.0
+    }
+
+    pub fn corners(&self) -> [Point3<f32>; 8] {
+        [
+            Point3::new(self.min.x, self.min.y, self.min.z),
+            Point3::new(self.max.x, self.min.y, self.min.z),
+            Point3::new(self.min.x, self.max.y, self.min.z),
+            Point3::new(self.max.x, self.max.y, self.min.z),
+            Point3::new(self.min.x, self.min.y, self.max.z),
+            Point3::new(self.max.x, self.min.y, self.max.z),
+            Point3::new(self.min.x, self.max.y, self.max.z),
+            Point3::new(self.max.x, self.max.y, self.max.z),
+        ]
+    }
+
+    /// Box containing this one once transformed by `matrix`
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Aabb {
+        Aabb::from_points(self.corners().iter().map(|corner| matrix.transform_point(corner))).unwrap()
+    }
+
+    pub fn contains_point(&self, point: &Point3<f32>) -> bool {
+        point.x >= self.min.x && point.x <= self.max.x
+            && point.y >= self.min.y && point.y <= self.max.y
+            && point.z >= self.min.z && point.z <= self.max.z
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Point3<f32>,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn new(center: Point3<f32>, radius: f32) -> BoundingSphere {
+        BoundingSphere { center, radius }
+    }
+
+    /// Sphere going through the corners of the box
+    pub fn from_aabb(aabb: &Aabb) -> BoundingSphere {
+        BoundingSphere::new(aabb.center(), aabb.half_extents().norm())
+    }
+
+    /// Sphere containing this one once transformed by `matrix`
+    /// The radius grows with the biggest scale of the matrix
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> BoundingSphere {
+        let scale = (0..3)
+            .map(|column| matrix.fixed_slice::<3, 1>(0, column).norm())
+            .fold(0.0f32, f32::max);
+
+        BoundingSphere::new(matrix.transform_point(&self.center), self.radius * scale)
+    }
+}
@@ -1,10 +1,100 @@
-use nalgebra::{Matrix4, Perspective3, Point, Point3, Unit, UnitQuaternion, Vector3};
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
-use sdl2::mouse::MouseUtil;
+use gl::types::GLenum;
+use nalgebra::{Matrix4, Orthographic3, Perspective3, Point3, Vector3};
 
-use sdl2::video::{Window};
+use crate::graphics::frustum::Frustum;
 use crate::graphics::program::Program;
+use crate::graphics::ray::Ray;
+use crate::input::Input;
+use crate::windsdl::{DrawableSize, ResizeListener};
+
+/// How the view space is projected on the screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective { fov_deg : f32, near : f32, far : f32 },
+    /// `height` is the visible height in world units, the width follows the aspect ratio
+    Orthographic { height : f32, near : f32, far : f32 },
+    /// Perspective without far plane, storing 1 at the near plane and 0 at infinity
+    /// for a better depth precision, requires `configure_depth`
+    ReverseZInfinite { fov_deg : f32, near : f32 },
+}
+
+impl Projection {
+    pub fn matrix(&self, aspect_ratio : f32) -> Matrix4<f32> {
+        match *self {
+            Projection::Perspective { fov_deg, near, far } => {
+                Perspective3::new(aspect_ratio, fov_deg.to_radians(), near, far).to_homogeneous()
+            }
+            Projection::Orthographic { height, near, far } => {
+                let half_height = height / 2.0;
+                let half_width = half_height * aspect_ratio;
+                Orthographic3::new(-half_width, half_width, -half_height, half_height, near, far).to_homogeneous()
+            }
+            Projection::ReverseZInfinite { fov_deg, near } => {
+                let focal = 1.0 / (fov_deg.to_radians() / 2.0).tan();
+                Matrix4::new(
+                    focal / aspect_ratio, 0.0, 0.0, 0.0,
+                    0.0, focal, 0.0, 0.0,
+                    0.0, 0.0, 0.0, near,
+                    0.0, 0.0, -1.0, 0.0,
+                )
+            }
+        }
+    }
+
+    /// Depth test that keeps the closest fragments with this projection
+    pub fn depth_func(&self) -> GLenum {
+        match self {
+            Projection::ReverseZInfinite { .. } => gl::GREATER,
+            _ => gl::LESS,
+        }
+    }
+
+    /// Reversed depth needs glClipControl, core since GL 4.5
+    pub fn is_supported(&self, gl : &gl::Gl) -> bool {
+        match self {
+            Projection::ReverseZInfinite { .. } => supports_clip_control(gl),
+            _ => true,
+        }
+    }
+
+    /// Sets the depth range, test and clear value the projection expects
+    /// Fails without changing anything if the context doesn't support the projection
+    pub fn configure_depth(&self, gl : &gl::Gl) -> Result<(), String> {
+        if !self.is_supported(gl) {
+            return Err(String::from("reversed depth needs OpenGL 4.5 or GL_ARB_clip_control"));
+        }
+
+        gl.depth_func(self.depth_func());
+
+        unsafe {
+            match self {
+                Projection::ReverseZInfinite { .. } => {
+                    gl.ClipControl(gl::LOWER_LEFT, gl::ZERO_TO_ONE);
+                    gl.ClearDepth(0.0);
+                }
+                _ => {
+                    // -1 to 1 is the default, only undo a previous reversed depth
+                    if supports_clip_control(gl) {
+                        gl.ClipControl(gl::LOWER_LEFT, gl::NEGATIVE_ONE_TO_ONE);
+                    }
+                    gl.ClearDepth(1.0);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn supports_clip_control(gl : &gl::Gl) -> bool {
+    gl.supports_version(4, 5) || gl.has_extension("GL_ARB_clip_control")
+}
+
+impl Default for Projection {
+    fn default() -> Projection {
+        Projection::Perspective { fov_deg : 45.0, near : 0.1, far : 100.0 }
+    }
+}
 
 pub struct Camera {
     gl : gl::Gl,
@@ -14,9 +104,18 @@ pub struct Camera {
 
     pub width : i32,
     pub height : i32,
+    pub projection : Projection,
 
+    /// Movement speed in units per second
     pub speed : f32,
-    pub sensitivity : f32,
+
+    /// How fast the camera reaches its speed, in units per second squared
+    /// 0 makes it start and stop instantly
+    pub acceleration : f32,
+    /// Exponential slow down applied when no movement key is held
+    pub damping : f32,
+    pub sprint_multiplier : f32,
+    pub velocity : Vector3<f32>,
 }
 
 impl Camera {
@@ -30,86 +129,122 @@ impl Camera {
 
             width,
             height,
+            projection : Projection::default(),
+
+            speed : 2.0,
 
-            speed : 0.1,
-            sensitivity : 100.0,
+            acceleration : 20.0,
+            damping : 10.0,
+            sprint_multiplier : 3.0,
+            velocity : Vector3::zeros(),
         }
     }
 
-    // let aspect_ratio = WIDTH as f32 / HEIGHT as f32;
-    // let fov = 45.0f32.to_radians();
-    // proj = Perspective3::new(aspect_ratio, fov, 0.1, 100.0).to_homogeneous();
+    pub fn aspect_ratio(&self) -> f32 {
+        if self.height <= 0 {
+            return 1.0;
+        }
+
+        self.width as f32 / self.height as f32
+    }
+
+    pub fn view_matrix(&self) -> Matrix4<f32> {
+        Matrix4::look_at_rh(&self.position, &(self.position + self.orientation), &self.up)
+    }
+
+    pub fn projection_matrix(&self) -> Matrix4<f32> {
+        self.projection.matrix(self.aspect_ratio())
+    }
+
+    pub fn view_projection_matrix(&self) -> Matrix4<f32> {
+        self.projection_matrix() * self.view_matrix()
+    }
+
+    /// Planes of the volume seen by the camera, to cull what is outside
+    pub fn frustum(&self) -> Frustum {
+        let depth_zero_to_one = matches!(self.projection, Projection::ReverseZInfinite { .. });
+        Frustum::from_matrix(&self.view_projection_matrix(), depth_zero_to_one)
+    }
+
+    /// World space ray going through the pixel `x`, `y` of the drawable area (origin at the top left)
+    pub fn screen_ray(&self, x : f32, y : f32) -> Ray {
+        let ndc_x = 2.0 * x / self.width as f32 - 1.0;
+        let ndc_y = 1.0 - 2.0 * y / self.height as f32;
 
-    pub fn matrix(&self, fov_deg : f32, near_plane : f32, far_plane : f32, shader_program : &Program, uniform : &str){
-        let mut view : Matrix4<f32> = Matrix4::identity();
-        let mut projection : Matrix4<f32> = Matrix4::identity();
+        // Depth of the near plane and of a point further away, both finite
+        let (near_depth, far_depth) = match self.projection {
+            Projection::ReverseZInfinite { .. } => (1.0, 0.5),
+            _ => (-1.0, 1.0),
+        };
 
-        view = Matrix4::look_at_rh(&self.position, &(self.position + self.orientation), &self.up);
+        let inverse = self.view_projection_matrix()
+            .try_inverse()
+            .unwrap_or_else(Matrix4::identity);
 
-        let aspect_ratio: f32 = (self.width / self.height) as f32;
-        projection = Perspective3::new(aspect_ratio, fov_deg.to_radians(), near_plane, far_plane).to_homogeneous();
+        let near = inverse.transform_point(&Point3::new(ndc_x, ndc_y, near_depth));
+        let far = inverse.transform_point(&Point3::new(ndc_x, ndc_y, far_depth));
 
+        Ray::new(near, (far - near).normalize())
+    }
+
+    /// Uploads the view-projection matrix to `uniform`
+    pub fn matrix(&self, shader_program : &Program, uniform : &str){
         unsafe {
             self.gl.UniformMatrix4fv(
                 shader_program.get_uniform_id(uniform),
                 1,
                 gl::FALSE,
-                (projection * view).as_ptr()
+                self.view_projection_matrix().as_ptr()
             );
         }
     }
 
-    pub fn keyboard_inputs(&mut self, event : &Event){
-        match event {
-            Event::KeyDown { keycode: Some(Keycode::W), .. } => {
-                self.position += self.speed * self.orientation
-            },
-            Event::KeyDown { keycode: Some(Keycode::S), .. } => {
-                self.position -= self.speed * self.orientation
-            },
-            Event::KeyDown { keycode: Some(Keycode::A), .. } => {
-                let direction = Vector3::cross(&self.orientation, &self.up).normalize();
-                self.position += self.speed * -direction
-            },
-            Event::KeyDown { keycode: Some(Keycode::D), .. } => {
-                let direction = Vector3::cross(&self.orientation, &self.up).normalize();
-                self.position -= self.speed * -direction
-            },
-            Event::KeyDown { keycode: Some(Keycode::LShift), .. } => {
-                self.position += self.speed * -self.up
-            },
-            Event::KeyDown { keycode: Some(Keycode::Space), .. } => {
-                self.position += self.speed * self.up
-            },
-            _ => { }
-        }
-    }
-
-    pub fn mouse_inputs(&mut self, event: &Event, mouse : &MouseUtil, window: &Window){
-            // println!("Mouse moved to ({}, {})", x, y);
-            // window.update_mouse_center(x, y);
-        match event {
-            Event::MouseMotion { x, y, .. } => {
-                println!("{} | {}", x, y);
-
-                let rot_x = (self.sensitivity * (y - (self.height) / 2) as f32) / self.height as f32;
-                let rot_y = (self.sensitivity * (x - (self.width) / 2) as f32) / self.width as f32;
-
-                let rotation_axis = Vector3::cross(&self.orientation, &self.up);
+    /// Moves the camera according to the `move_*` axes, `dt` being the frame time in seconds
+    pub fn update(&mut self, input : &Input, dt : f32){
+        let right = Vector3::cross(&self.orientation, &self.up).normalize();
 
-                let x_rotation = UnitQuaternion::from_axis_angle(&Unit::new_normalize(rotation_axis), -rot_x.to_radians());
-                let y_rotation = UnitQuaternion::from_axis_angle(&Unit::new_normalize(self.up), -rot_y.to_radians());
+        let direction = self.orientation * input.axis("move_forward")
+            + right * input.axis("move_right")
+            + self.up * input.axis("move_up");
 
-                let mut new_orientation = x_rotation * self.orientation;
+        self.integrate_movement(direction, input.is_pressed("sprint"), dt);
+    }
 
-                new_orientation = y_rotation * new_orientation;
+    /// Accelerates towards `direction` (zero to slow down) and moves the camera
+    /// Used by `update` and by the camera controllers
+    pub fn integrate_movement(&mut self, direction : Vector3<f32>, sprinting : bool, dt : f32){
+        let moving = direction.norm_squared() > 0.0;
 
-                self.orientation = new_orientation;
+        let mut target_speed = self.speed;
+        if sprinting {
+            target_speed *= self.sprint_multiplier;
+        }
 
-                mouse.warp_mouse_in_window(&window, self.width / 2, self.height / 2)
+        let target_velocity = if moving { direction.normalize() * target_speed } else { Vector3::zeros() };
+
+        if !moving && self.damping > 0.0 {
+            self.velocity *= (-self.damping * dt).exp();
+        } else if self.acceleration <= 0.0 {
+            self.velocity = target_velocity;
+        } else {
+            // Accelerate towards the target without overshooting it
+            let difference = target_velocity - self.velocity;
+            let max_change = self.acceleration * dt;
+
+            if difference.norm() <= max_change {
+                self.velocity = target_velocity;
+            } else {
+                self.velocity += difference.normalize() * max_change;
             }
-            _ => { }
         }
 
+        self.position += self.velocity * dt;
+    }
+}
+
+impl ResizeListener for Camera {
+    fn on_resize(&mut self, size : DrawableSize) {
+        self.width = size.width as i32;
+        self.height = size.height as i32;
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,217 @@
+use nalgebra::{Point3, Unit, UnitQuaternion, Vector3};
+
+use crate::graphics::camera::Camera;
+use crate::input::Input;
+
+/// Drives a `Camera` from the user inputs
+/// Controllers can be swapped at runtime, `activate` syncs them with the camera they take over
+pub trait CameraController {
+    /// Reads the camera state so the switch doesn't make it jump
+    fn activate(&mut self, camera: &mut Camera);
+
+    /// Called once per frame with the frame time in seconds
+    /// Uses the `move_*`, `look_*`, `turn_*`, `roll` and `zoom` axes and the `sprint` action
+    fn update(&mut self, camera: &mut Camera, input: &Input, dt: f32);
+}
+
+/// Yaw and pitch in radians of a direction, yaw 0 looking towards -Z
+fn yaw_pitch(direction: &Vector3<f32>) -> (f32, f32) {
+    let direction = direction.normalize();
+    (direction.x.atan2(-direction.z), direction.y.clamp(-1.0, 1.0).asin())
+}
+
+/// Mouse movement plus the stick turn rates (per second) over the frame, in pixels
+fn look_delta(input: &Input, dt: f32) -> (f32, f32) {
+    (
+        input.axis("look_x") + input.axis("turn_x") * dt,
+        input.axis("look_y") + input.axis("turn_y") * dt,
+    )
+}
+
+fn direction_from(yaw: f32, pitch: f32) -> Vector3<f32> {
+    Vector3::new(yaw.sin() * pitch.cos(), pitch.sin(), -yaw.cos() * pitch.cos())
+}
+
+/// First person camera: the up vector never rolls and the pitch stops before the poles
+pub struct FpsController {
+    yaw: f32,
+    pitch: f32,
+    /// Degrees per pixel of mouse movement
+    pub sensitivity: f32,
+    pub max_pitch: f32,
+}
+
+impl Default for FpsController {
+    fn default() -> FpsController {
+        FpsController::new()
+    }
+}
+
+impl FpsController {
+    pub fn new() -> FpsController {
+        FpsController {
+            yaw: 0.0,
+            pitch: 0.0,
+            sensitivity: 0.1,
+            max_pitch: 89.0f32.to_radians(),
+        }
+    }
+}
+
+impl CameraController for FpsController {
+    fn activate(&mut self, camera: &mut Camera) {
+        let (yaw, pitch) = yaw_pitch(&camera.orientation);
+        self.yaw = yaw;
+        self.pitch = pitch.clamp(-self.max_pitch, self.max_pitch);
+
+        camera.up = Vector3::y();
+        camera.orientation = direction_from(self.yaw, self.pitch);
+    }
+
+    fn update(&mut self, camera: &mut Camera, input: &Input, dt: f32) {
+        let (look_x, look_y) = look_delta(input, dt);
+
+        self.yaw += (look_x * self.sensitivity).to_radians();
+        self.pitch -= (look_y * self.sensitivity).to_radians();
+        self.pitch = self.pitch.clamp(-self.max_pitch, self.max_pitch);
+
+        camera.orientation = direction_from(self.yaw, self.pitch);
+
+        // Walk on the horizontal plane whatever the pitch is
+        let forward = Vector3::new(self.yaw.sin(), 0.0, -self.yaw.cos());
+        let right = Vector3::new(self.yaw.cos(), 0.0, self.yaw.sin());
+
+        let direction = forward * input.axis("move_forward")
+            + right * input.axis("move_right")
+            + Vector3::y() * input.axis("move_up");
+
+        camera.integrate_movement(direction, input.is_pressed("sprint"), dt);
+    }
+}
+
+/// Rotates around a target point, the mouse wheel zooms in and out
+pub struct OrbitController {
+    pub target: Point3<f32>,
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// Distance multiplier applied per wheel step
+    pub zoom_factor: f32,
+    yaw: f32,
+    pitch: f32,
+    /// Degrees per pixel of mouse movement
+    pub sensitivity: f32,
+}
+
+impl OrbitController {
+    pub fn new(target: Point3<f32>) -> OrbitController {
+        OrbitController {
+            target,
+            distance: 3.0,
+            min_distance: 0.5,
+            max_distance: 50.0,
+            zoom_factor: 0.9,
+            yaw: 0.0,
+            pitch: 0.0,
+            sensitivity: 0.2,
+        }
+    }
+
+    fn place_camera(&self, camera: &mut Camera) {
+        let direction = direction_from(self.yaw, self.pitch);
+
+        camera.position = self.target - direction * self.distance;
+        camera.orientation = direction;
+        camera.up = Vector3::y();
+        camera.velocity = Vector3::zeros();
+    }
+}
+
+impl CameraController for OrbitController {
+    fn activate(&mut self, camera: &mut Camera) {
+        let offset = self.target - camera.position;
+        let distance = offset.norm();
+
+        if distance > 0.0 {
+            let (yaw, pitch) = yaw_pitch(&offset);
+            self.yaw = yaw;
+            self.pitch = pitch.clamp(-89.0f32.to_radians(), 89.0f32.to_radians());
+            self.distance = distance.clamp(self.min_distance, self.max_distance);
+        }
+
+        self.place_camera(camera);
+    }
+
+    fn update(&mut self, camera: &mut Camera, input: &Input, dt: f32) {
+        let (look_x, look_y) = look_delta(input, dt);
+
+        self.yaw += (look_x * self.sensitivity).to_radians();
+        self.pitch -= (look_y * self.sensitivity).to_radians();
+        self.pitch = self.pitch.clamp(-89.0f32.to_radians(), 89.0f32.to_radians());
+
+        self.distance *= self.zoom_factor.powf(input.axis("zoom"));
+        self.distance = self.distance.clamp(self.min_distance, self.max_distance);
+
+        self.place_camera(camera);
+    }
+}
+
+/// Six degrees of freedom camera, it can roll and loop over the poles
+pub struct FreeFlyController {
+    rotation: UnitQuaternion<f32>,
+    /// Degrees per pixel of mouse movement
+    pub sensitivity: f32,
+    /// Degrees per second
+    pub roll_speed: f32,
+}
+
+impl Default for FreeFlyController {
+    fn default() -> FreeFlyController {
+        FreeFlyController::new()
+    }
+}
+
+impl FreeFlyController {
+    pub fn new() -> FreeFlyController {
+        FreeFlyController {
+            rotation: UnitQuaternion::identity(),
+            sensitivity: 0.1,
+            roll_speed: 90.0,
+        }
+    }
+
+    fn apply_rotation(&self, camera: &mut Camera) {
+        camera.orientation = self.rotation * -Vector3::z();
+        camera.up = self.rotation * Vector3::y();
+    }
+}
+
+impl CameraController for FreeFlyController {
+    fn activate(&mut self, camera: &mut Camera) {
+        // Rotation taking -Z to the orientation and Y to the up vector
+        self.rotation = UnitQuaternion::face_towards(&-camera.orientation, &camera.up);
+        self.apply_rotation(camera);
+    }
+
+    fn update(&mut self, camera: &mut Camera, input: &Input, dt: f32) {
+        let (look_x, look_y) = look_delta(input, dt);
+
+        // Rotate around the camera's own axes
+        let yaw = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), -(look_x * self.sensitivity).to_radians());
+        let pitch = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), -(look_y * self.sensitivity).to_radians());
+        let roll = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), (input.axis("roll") * self.roll_speed * dt).to_radians());
+
+        self.rotation = self.rotation * yaw * pitch * roll;
+        self.apply_rotation(camera);
+
+        let forward = camera.orientation;
+        let up = camera.up;
+        let right = Unit::new_normalize(forward.cross(&up)).into_inner();
+
+        let direction = forward * input.axis("move_forward")
+            + right * input.axis("move_right")
+            + up * input.axis("move_up");
+
+        camera.integrate_movement(direction, input.is_pressed("sprint"), dt);
+    }
+}
@@ -0,0 +1,375 @@
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+
+use crate::graphics::camera::{Camera, Projection};
+
+/// Camera state at a point in time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraKeyframe {
+    pub time: f32,
+    pub position: Point3<f32>,
+    /// Rotation taking -Z to the camera orientation and Y to its up vector
+    pub rotation: UnitQuaternion<f32>,
+    pub fov_deg: f32,
+}
+
+impl CameraKeyframe {
+    pub fn new(time: f32, position: Point3<f32>, target: Point3<f32>, fov_deg: f32) -> CameraKeyframe {
+        CameraKeyframe {
+            time,
+            position,
+            rotation: UnitQuaternion::face_towards(&(position - target), &Vector3::y()),
+            fov_deg,
+        }
+    }
+
+    pub fn from_camera(camera: &Camera, time: f32) -> CameraKeyframe {
+        let fov_deg = match camera.projection {
+            Projection::Perspective { fov_deg, .. } | Projection::ReverseZInfinite { fov_deg, .. } => fov_deg,
+            Projection::Orthographic { .. } => 45.0,
+        };
+
+        CameraKeyframe {
+            time,
+            position: camera.position,
+            rotation: UnitQuaternion::face_towards(&-camera.orientation, &camera.up),
+            fov_deg,
+        }
+    }
+
+    /// Moves the camera to this state, the FOV is ignored by orthographic projections
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.position = self.position;
+        camera.orientation = self.rotation * -Vector3::z();
+        camera.up = self.rotation * Vector3::y();
+        camera.velocity = Vector3::zeros();
+
+        match &mut camera.projection {
+            Projection::Perspective { fov_deg, .. } | Projection::ReverseZInfinite { fov_deg, .. } => {
+                *fov_deg = self.fov_deg;
+            }
+            Projection::Orthographic { .. } => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathInterpolation {
+    /// Goes through every keyframe
+    CatmullRom,
+    /// Uses the keyframes as control points of a single curve,
+    /// only the first and last ones are reached
+    Bezier,
+}
+
+#[derive(Debug, Clone)]
+pub struct CameraPath {
+    keyframes: Vec<CameraKeyframe>,
+    pub interpolation: PathInterpolation,
+}
+
+impl CameraPath {
+    pub fn new(interpolation: PathInterpolation) -> CameraPath {
+        CameraPath {
+            keyframes: Vec::new(),
+            interpolation,
+        }
+    }
+
+    /// Inserts the keyframe, keeping them sorted by time
+    pub fn add_keyframe(&mut self, keyframe: CameraKeyframe) {
+        let index = self.keyframes.iter()
+            .position(|other| other.time > keyframe.time)
+            .unwrap_or(self.keyframes.len());
+
+        self.keyframes.insert(index, keyframe);
+    }
+
+    pub fn keyframes(&self) -> &[CameraKeyframe] {
+        &self.keyframes
+    }
+
+    pub fn duration(&self) -> f32 {
+        match (self.keyframes.first(), self.keyframes.last()) {
+            (Some(first), Some(last)) => last.time - first.time,
+            _ => 0.0,
+        }
+    }
+
+    /// Camera state at `time`, clamped to the path ends
+    pub fn sample(&self, time: f32) -> Option<CameraKeyframe> {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+
+        if self.keyframes.len() == 1 || time <= first.time {
+            return Some(CameraKeyframe { time, ..*first });
+        }
+        if time >= last.time {
+            return Some(CameraKeyframe { time, ..*last });
+        }
+
+        // Segment containing `time` and the position inside it
+        let segment = self.keyframes.iter().rposition(|keyframe| keyframe.time <= time).unwrap();
+        let from = &self.keyframes[segment];
+        let to = &self.keyframes[segment + 1];
+        let t = (time - from.time) / (to.time - from.time);
+
+        let (position, rotation, fov_deg) = match self.interpolation {
+            PathInterpolation::CatmullRom => {
+                let previous = &self.keyframes[segment.saturating_sub(1)];
+                let next = &self.keyframes[(segment + 2).min(self.keyframes.len() - 1)];
+
+                let position = catmull_rom(
+                    previous.position.coords, from.position.coords, to.position.coords, next.position.coords, t
+                );
+                let fov = catmull_rom(
+                    Vector3::repeat(previous.fov_deg), Vector3::repeat(from.fov_deg),
+                    Vector3::repeat(to.fov_deg), Vector3::repeat(next.fov_deg), t
+                );
+
+                (Point3::from(position), slerp(&from.rotation, &to.rotation, t), fov.x)
+            }
+            PathInterpolation::Bezier => {
+                let t = (time - first.time) / self.duration();
+
+                let points: Vec<Vector3<f32>> = self.keyframes.iter()
+                    .map(|keyframe| keyframe.position.coords)
+                    .collect();
+                let rotations: Vec<UnitQuaternion<f32>> = self.keyframes.iter()
+                    .map(|keyframe| keyframe.rotation)
+                    .collect();
+                let fovs: Vec<Vector3<f32>> = self.keyframes.iter()
+                    .map(|keyframe| Vector3::repeat(keyframe.fov_deg))
+                    .collect();
+
+                // Same curve parameter for everything so the orientation follows the position
+                (Point3::from(de_casteljau(points, t)), de_casteljau_rotation(rotations, t), de_casteljau(fovs, t).x)
+            }
+        };
+
+        Some(CameraKeyframe { time, position, rotation, fov_deg })
+    }
+}
+
+fn catmull_rom(p0: Vector3<f32>, p1: Vector3<f32>, p2: Vector3<f32>, p3: Vector3<f32>, t: f32) -> Vector3<f32> {
+    let t2 = t * t;
+    let t3 = t2 * t;
+
+    ((p1 * 2.0)
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3) * 0.5
+}
+
+fn de_casteljau(mut points: Vec<Vector3<f32>>, t: f32) -> Vector3<f32> {
+    while points.len() > 1 {
+        for i in 0..points.len() - 1 {
+            points[i] = points[i].lerp(&points[i + 1], t);
+        }
+        points.pop();
+    }
+
+    points[0]
+}
+
+/// De Casteljau with slerps in place of the lerps
+fn de_casteljau_rotation(mut rotations: Vec<UnitQuaternion<f32>>, t: f32) -> UnitQuaternion<f32> {
+    while rotations.len() > 1 {
+        for i in 0..rotations.len() - 1 {
+            rotations[i] = slerp(&rotations[i], &rotations[i + 1], t);
+        }
+        rotations.pop();
+    }
+
+    rotations[0]
+}
+
+/// Falls back to nlerp for rotations too close for slerp
+fn slerp(from: &UnitQuaternion<f32>, to: &UnitQuaternion<f32>, t: f32) -> UnitQuaternion<f32> {
+    from.try_slerp(to, t, 1.0e-6).unwrap_or_else(|| from.nlerp(to, t))
+}
+
+/// Plays a path from its first keyframe, driving the camera
+pub struct CameraPathPlayer {
+    pub path: CameraPath,
+    pub time: f32,
+    pub speed: f32,
+    pub looping: bool,
+    playing: bool,
+}
+
+impl CameraPathPlayer {
+    pub fn new(path: CameraPath) -> CameraPathPlayer {
+        CameraPathPlayer {
+            path,
+            time: 0.0,
+            speed: 1.0,
+            looping: false,
+            playing: false,
+        }
+    }
+
+    pub fn play(&mut self) {
+        self.time = self.path.keyframes().first().map_or(0.0, |first| first.time);
+        self.playing = true;
+    }
+
+    pub fn stop(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Advances the playback and moves the camera, does nothing when stopped
+    pub fn update(&mut self, camera: &mut Camera, dt: f32) {
+        if !self.playing {
+            return;
+        }
+
+        self.time += dt * self.speed;
+
+        let start = self.path.keyframes().first().map_or(0.0, |first| first.time);
+        let duration = self.path.duration();
+
+        if self.time >= start + duration {
+            if self.looping && duration > 0.0 {
+                self.time = start + (self.time - start) % duration;
+            } else {
+                self.time = start + duration;
+                self.playing = false;
+            }
+        }
+
+        if let Some(keyframe) = self.path.sample(self.time) {
+            keyframe.apply(camera);
+        }
+    }
+}
+
+/// Smooth "fly-to" from the current camera state to another one
+pub struct CameraTransition {
+    from: CameraKeyframe,
+    to: CameraKeyframe,
+    duration: f32,
+    elapsed: f32,
+}
+
+impl CameraTransition {
+    pub fn fly_to(camera: &Camera, to: CameraKeyframe, duration: f32) -> CameraTransition {
+        CameraTransition {
+            from: CameraKeyframe::from_camera(camera, 0.0),
+            to,
+            duration,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    /// Moves the camera, easing in and out of the motion
+    pub fn update(&mut self, camera: &mut Camera, dt: f32) {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+
+        let t = if self.duration > 0.0 { self.elapsed / self.duration } else { 1.0 };
+        let eased = t * t * (3.0 - 2.0 * t);
+
+        let keyframe = CameraKeyframe {
+            time: self.elapsed,
+            position: self.from.position + (self.to.position - self.from.position) * eased,
+            rotation: slerp(&self.from.rotation, &self.to.rotation, eased),
+            fov_deg: self.from.fov_deg + (self.to.fov_deg - self.from.fov_deg) * eased,
+        };
+
+        keyframe.apply(camera);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Point3, UnitQuaternion, Vector3};
+
+    use super::{CameraKeyframe, CameraPath, PathInterpolation};
+
+    fn path(interpolation: PathInterpolation) -> CameraPath {
+        let target = Point3::origin();
+        let mut path = CameraPath::new(interpolation);
+
+        // Added out of order on purpose
+        path.add_keyframe(CameraKeyframe::new(2.0, Point3::new(-4.0, 1.0, 0.0), target, 60.0));
+        path.add_keyframe(CameraKeyframe::new(0.0, Point3::new(0.0, 1.0, 4.0), target, 45.0));
+        path.add_keyframe(CameraKeyframe::new(1.0, Point3::new(4.0, 1.0, 0.0), target, 50.0));
+        path.add_keyframe(CameraKeyframe::new(3.0, Point3::new(0.0, 1.0, -4.0), target, 45.0));
+
+        path
+    }
+
+    fn assert_close(a: &Point3<f32>, b: &Point3<f32>) {
+        assert!((a - b).norm() < 1.0e-4, "{} is not {}", a, b);
+    }
+
+    #[test]
+    fn keyframes_are_sorted() {
+        let times: Vec<f32> = path(PathInterpolation::CatmullRom).keyframes().iter().map(|keyframe| keyframe.time).collect();
+
+        assert_eq!(times, vec![0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(path(PathInterpolation::CatmullRom).duration(), 3.0);
+    }
+
+    #[test]
+    fn empty_path_has_no_sample() {
+        assert!(CameraPath::new(PathInterpolation::Bezier).sample(0.0).is_none());
+    }
+
+    #[test]
+    fn samples_are_clamped_to_the_ends() {
+        for interpolation in [PathInterpolation::CatmullRom, PathInterpolation::Bezier] {
+            let path = path(interpolation);
+
+            assert_close(&path.sample(-1.0).unwrap().position, &Point3::new(0.0, 1.0, 4.0));
+            assert_close(&path.sample(10.0).unwrap().position, &Point3::new(0.0, 1.0, -4.0));
+        }
+    }
+
+    #[test]
+    fn catmull_rom_goes_through_the_keyframes() {
+        let path = path(PathInterpolation::CatmullRom);
+
+        for keyframe in path.keyframes() {
+            let sample = path.sample(keyframe.time).unwrap();
+
+            assert_close(&sample.position, &keyframe.position);
+            assert!(sample.rotation.angle_to(&keyframe.rotation) < 1.0e-3);
+            assert!((sample.fov_deg - keyframe.fov_deg).abs() < 1.0e-3);
+        }
+    }
+
+    #[test]
+    fn bezier_rotation_follows_the_curve_parameter() {
+        // Two keyframes: the curve is a line, position and rotation are both halfway at the middle
+        let mut path = CameraPath::new(PathInterpolation::Bezier);
+        let from = CameraKeyframe::new(0.0, Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 0.0, -1.0), 45.0);
+        let to = CameraKeyframe::new(4.0, Point3::new(8.0, 0.0, 0.0), Point3::new(9.0, 0.0, 0.0), 45.0);
+        path.add_keyframe(from);
+        path.add_keyframe(to);
+
+        let sample = path.sample(2.0).unwrap();
+        let halfway = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), -45.0f32.to_radians());
+
+        assert_close(&sample.position, &Point3::new(4.0, 0.0, 0.0));
+        assert!(sample.rotation.angle_to(&halfway) < 1.0e-3, "rotation {:?}", sample.rotation);
+    }
+
+    #[test]
+    fn bezier_is_smooth_across_keyframes() {
+        // With control points in the middle, the rotation doesn't snap at their times
+        let path = path(PathInterpolation::Bezier);
+        let before = path.sample(0.999).unwrap();
+        let after = path.sample(1.001).unwrap();
+
+        assert!(before.rotation.angle_to(&after.rotation) < 1.0e-2);
+        assert!((before.position - after.position).norm() < 1.0e-2);
+    }
+}
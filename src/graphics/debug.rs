@@ -0,0 +1,138 @@
+use std::ffi::{c_void, CStr};
+
+use gl::types::{GLchar, GLenum, GLsizei, GLuint};
+
+/// Severity of a debug message, most severe first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DebugSeverity {
+    High,
+    Medium,
+    Low,
+    Notification,
+}
+
+impl DebugSeverity {
+    fn from_gl(severity : GLenum) -> DebugSeverity {
+        match severity {
+            gl::DEBUG_SEVERITY_HIGH => DebugSeverity::High,
+            gl::DEBUG_SEVERITY_MEDIUM => DebugSeverity::Medium,
+            gl::DEBUG_SEVERITY_LOW => DebugSeverity::Low,
+            _ => DebugSeverity::Notification,
+        }
+    }
+}
+
+/// A message sent by the driver through KHR_debug
+#[derive(Debug, Clone)]
+pub struct DebugMessage {
+    pub source : &'static str,
+    pub kind : &'static str,
+    pub severity : DebugSeverity,
+    pub id : GLuint,
+    pub message : String,
+}
+
+pub fn source_name(source : GLenum) -> &'static str {
+    match source {
+        gl::DEBUG_SOURCE_API => "API",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
+        gl::DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    }
+}
+
+pub fn type_name(kind : GLenum) -> &'static str {
+    match kind {
+        gl::DEBUG_TYPE_ERROR => "error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behavior",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behavior",
+        gl::DEBUG_TYPE_PORTABILITY => "portability",
+        gl::DEBUG_TYPE_PERFORMANCE => "performance",
+        gl::DEBUG_TYPE_MARKER => "marker",
+        gl::DEBUG_TYPE_PUSH_GROUP => "push group",
+        gl::DEBUG_TYPE_POP_GROUP => "pop group",
+        _ => "other",
+    }
+}
+
+pub type DebugLogger = Box<dyn Fn(&DebugMessage)>;
+
+/// Errors and warnings on stderr, the rest on stdout down to `min_severity`
+pub fn default_logger(min_severity : DebugSeverity) -> DebugLogger {
+    Box::new(move |message : &DebugMessage| {
+        if message.severity > min_severity {
+            return;
+        }
+
+        let line = format!(
+            "[GL {:?}] {} {} {}: {}",
+            message.severity, message.source, message.kind, message.id, message.message
+        );
+        match message.severity {
+            DebugSeverity::High | DebugSeverity::Medium => eprintln!("{}", line),
+            DebugSeverity::Low | DebugSeverity::Notification => println!("{}", line),
+        }
+    })
+}
+
+extern "system" fn debug_callback(
+    source : GLenum,
+    kind : GLenum,
+    id : GLuint,
+    severity : GLenum,
+    _length : GLsizei,
+    message : *const GLchar,
+    logger : *mut c_void,
+) {
+    let message = DebugMessage {
+        source : source_name(source),
+        kind : type_name(kind),
+        severity : DebugSeverity::from_gl(severity),
+        id,
+        message : unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned(),
+    };
+
+    let logger = unsafe { &*(logger as *const DebugLogger) };
+    logger(&message);
+}
+
+/// True if the context supports KHR_debug (core since GL 4.3)
+/// Loaded functions don't mean the context has them, the version and extensions are queried once per context
+pub fn is_supported(gl : &gl::Gl) -> bool {
+    gl.supports_version(4, 3) || gl.has_extension("GL_KHR_debug")
+}
+
+/// Sends the driver messages to `logger`, synchronously so a breakpoint in it shows the faulty call
+/// The logger lives as long as the program, the context keeps a pointer to it
+/// Returns false if KHR_debug is not available
+pub fn enable_debug_output(gl : &gl::Gl, logger : DebugLogger) -> bool {
+    if !is_supported(gl) {
+        return false;
+    }
+
+    let logger = Box::into_raw(Box::new(logger));
+
+    unsafe {
+        gl.enable(gl::DEBUG_OUTPUT);
+        gl.enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        gl.DebugMessageCallback(Some(debug_callback), logger as *const c_void);
+        gl.DebugMessageControl(gl::DONT_CARE, gl::DONT_CARE, gl::DONT_CARE, 0, std::ptr::null(), gl::TRUE);
+    }
+
+    true
+}
+
+/// Names an object in the debug messages and in tools like RenderDoc, used by the `set_label` of every wrapper
+/// `identifier` is the kind of object (gl::BUFFER, gl::TEXTURE...), it has to be bound once before
+/// Does nothing without KHR_debug
+pub fn object_label(gl : &gl::Gl, identifier : GLenum, id : GLuint, label : &str) {
+    if !is_supported(gl) {
+        return;
+    }
+
+    unsafe {
+        gl.ObjectLabel(identifier, id, label.len() as GLsizei, label.as_ptr() as *const GLchar);
+    }
+}
@@ -1,4 +1,5 @@
 use gl::types::GLuint;
+use crate::graphics::debug;
 
 pub struct EBO {
     gl : gl::Gl,
@@ -6,15 +7,16 @@ pub struct EBO {
 }
 
 impl EBO{
-    pub fn new(gl : &gl::Gl, indices : &Vec<GLuint>) -> EBO{
+    pub fn new(gl : &gl::Gl, indices : &[GLuint]) -> EBO{
         let mut ebo: GLuint = 0;
 
+        unsafe { gl.GenBuffers(1, &mut ebo); }
+
+        gl.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
         unsafe {
-            gl.GenBuffers(1, &mut ebo);
-            gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
             gl.BufferData(
                 gl::ELEMENT_ARRAY_BUFFER,
-                (indices.len() * std::mem::size_of::<f32>()) as gl::types::GLsizeiptr,
+                std::mem::size_of_val(indices) as gl::types::GLsizeiptr,
                 indices.as_ptr() as *const gl::types::GLvoid,
                 gl::STATIC_DRAW
             );
@@ -26,15 +28,25 @@ impl EBO{
         }
     }
 
+    /// Hands the indices over to another context of the share group, bind them in a vertex array created there
+    pub fn adopt(&mut self, gl : &gl::Gl){
+        debug_assert!(self.gl.shares_objects_with(gl), "buffer {} is not shared with GL context {}", self.id, gl.context_id());
+        self.gl = gl.clone();
+    }
+
+    pub fn set_label(&self, label : &str){
+        debug::object_label(&self.gl, gl::BUFFER, self.id, label);
+    }
+
     pub fn bind(&self){
-        unsafe { self.gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.id); }
+        self.gl.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, self.id);
     }
 
     pub fn unbind(&self){
-        unsafe { self.gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0); }
+        self.gl.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, 0);
     }
 
     pub fn delete(&self){
-        unsafe { self.gl.DeleteBuffers(1, &self.id); }
+        self.gl.delete_buffer(self.id);
     }
 }
\ No newline at end of file
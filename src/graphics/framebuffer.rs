@@ -0,0 +1,112 @@
+use gl::types::{GLsizei, GLuint};
+
+use crate::windsdl::{DrawableSize, ResizeListener};
+
+/// Off-screen render target with a color texture and a depth-stencil renderbuffer
+/// Its attachments are recreated whenever the window is resized
+pub struct Framebuffer {
+    gl : gl::Gl,
+    pub id: GLuint,
+    pub color_texture: GLuint,
+    depth_renderbuffer: GLuint,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Framebuffer {
+    pub fn new(gl : &gl::Gl, width : u32, height : u32) -> Framebuffer {
+        let mut framebuffer = Framebuffer {
+            gl : gl.clone(),
+            id : 0,
+            color_texture : 0,
+            depth_renderbuffer : 0,
+            width,
+            height,
+        };
+
+        unsafe { gl.GenFramebuffers(1, &mut framebuffer.id); }
+        framebuffer.create_attachments();
+
+        framebuffer
+    }
+
+    fn create_attachments(&mut self) {
+        let gl = &self.gl;
+
+        unsafe {
+            gl.BindFramebuffer(gl::FRAMEBUFFER, self.id);
+
+            gl.GenTextures(1, &mut self.color_texture);
+            gl.bind_texture(gl::TEXTURE_2D, self.color_texture);
+            gl.TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA8 as i32,
+                self.width as GLsizei,
+                self.height as GLsizei,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null()
+            );
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl.bind_texture(gl::TEXTURE_2D, 0);
+            gl.FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, self.color_texture, 0);
+
+            gl.GenRenderbuffers(1, &mut self.depth_renderbuffer);
+            gl.BindRenderbuffer(gl::RENDERBUFFER, self.depth_renderbuffer);
+            gl.RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH24_STENCIL8, self.width as GLsizei, self.height as GLsizei);
+            gl.BindRenderbuffer(gl::RENDERBUFFER, 0);
+            gl.FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::RENDERBUFFER, self.depth_renderbuffer);
+
+            gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    fn delete_attachments(&mut self) {
+        self.gl.delete_texture(self.color_texture);
+        unsafe { self.gl.DeleteRenderbuffers(1, &self.depth_renderbuffer); }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        let status = unsafe {
+            self.gl.BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            self.gl.CheckFramebufferStatus(gl::FRAMEBUFFER)
+        };
+        self.unbind();
+
+        status == gl::FRAMEBUFFER_COMPLETE
+    }
+
+    /// Renders into the framebuffer, the viewport is set to its size
+    pub fn bind(&self) {
+        self.gl.debug_assert_current();
+        unsafe {
+            self.gl.BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            self.gl.Viewport(0, 0, self.width as GLsizei, self.height as GLsizei);
+        }
+    }
+
+    pub fn unbind(&self) {
+        unsafe { self.gl.BindFramebuffer(gl::FRAMEBUFFER, 0); }
+    }
+
+    pub fn delete(&mut self) {
+        self.delete_attachments();
+        unsafe { self.gl.DeleteFramebuffers(1, &self.id); }
+    }
+}
+
+impl ResizeListener for Framebuffer {
+    fn on_resize(&mut self, size : DrawableSize) {
+        if size.width == self.width && size.height == self.height {
+            return;
+        }
+
+        self.delete_attachments();
+        self.width = size.width;
+        self.height = size.height;
+        self.create_attachments();
+    }
+}
@@ -0,0 +1,177 @@
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+
+use crate::graphics::bounds::{Aabb, BoundingSphere};
+
+/// Plane of equation `normal . p + distance = 0`, the normal pointing inside the frustum
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plane {
+    pub normal: Vector3<f32>,
+    pub distance: f32,
+}
+
+impl Plane {
+    /// Builds a plane from its a, b, c, d coefficients and normalizes it
+    /// Returns `None` for degenerate planes (e.g. the far plane of an infinite projection)
+    fn from_coefficients(coefficients: Vector4<f32>) -> Option<Plane> {
+        let normal = Vector3::new(coefficients.x, coefficients.y, coefficients.z);
+        let length = normal.norm();
+
+        if length <= f32::EPSILON {
+            return None;
+        }
+
+        Some(Plane {
+            normal: normal / length,
+            distance: coefficients.w / length,
+        })
+    }
+
+    /// Positive in front of the plane, negative behind
+    pub fn signed_distance(&self, point: &Point3<f32>) -> f32 {
+        self.normal.dot(&point.coords) + self.distance
+    }
+}
+
+/// Volume seen by a camera, described by its (up to six) planes
+#[derive(Debug, Clone)]
+pub struct Frustum {
+    pub planes: Vec<Plane>,
+}
+
+impl Frustum {
+    /// Extracts the planes of a view-projection matrix (Gribb & Hartmann)
+    /// `depth_zero_to_one` is true when the projection maps depth to [0, 1] instead of [-1, 1]
+    pub fn from_matrix(view_projection: &Matrix4<f32>, depth_zero_to_one: bool) -> Frustum {
+        let row = |index: usize| -> Vector4<f32> { view_projection.row(index).transpose() };
+
+        let near = if depth_zero_to_one { row(2) } else { row(3) + row(2) };
+
+        let coefficients = [
+            row(3) + row(0), // left
+            row(3) - row(0), // right
+            row(3) + row(1), // bottom
+            row(3) - row(1), // top
+            near,
+            row(3) - row(2), // far
+        ];
+
+        Frustum {
+            planes: coefficients.iter().filter_map(|&plane| Plane::from_coefficients(plane)).collect(),
+        }
+    }
+
+    pub fn contains_point(&self, point: &Point3<f32>) -> bool {
+        self.planes.iter().all(|plane| plane.signed_distance(point) >= 0.0)
+    }
+
+    /// True if the box is at least partly inside
+    /// Conservative: boxes near the frustum corners may be reported as visible
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // Corner of the box the furthest along the plane normal
+            let positive = Point3::new(
+                if plane.normal.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if plane.normal.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if plane.normal.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+            );
+
+            plane.signed_distance(&positive) >= 0.0
+        })
+    }
+
+    /// True if the sphere is at least partly inside
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes.iter().all(|plane| plane.signed_distance(&sphere.center) >= -sphere.radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Matrix4, Perspective3, Point3, Vector3};
+
+    use super::Frustum;
+    use crate::graphics::bounds::{Aabb, BoundingSphere};
+    use crate::graphics::camera::Projection;
+
+    /// Camera at the origin looking down -z, 90° field of view, square aspect, near 1 and far 100
+    /// so the frustum is |x| <= -z, |y| <= -z, -100 <= z <= -1
+    fn frustum() -> Frustum {
+        let projection = Perspective3::new(1.0, 90.0f32.to_radians(), 1.0, 100.0).to_homogeneous();
+        Frustum::from_matrix(&projection, false)
+    }
+
+    fn cube(center: Point3<f32>, half_size: f32) -> Aabb {
+        let half = Vector3::new(half_size, half_size, half_size);
+        Aabb::new(center - half, center + half)
+    }
+
+    #[test]
+    fn aabb_inside() {
+        assert!(frustum().intersects_aabb(&cube(Point3::new(0.0, 0.0, -10.0), 1.0)));
+    }
+
+    #[test]
+    fn aabb_outside() {
+        let frustum = frustum();
+
+        // Behind the camera, on the left, above, past the far plane
+        assert!(!frustum.intersects_aabb(&cube(Point3::new(0.0, 0.0, 5.0), 1.0)));
+        assert!(!frustum.intersects_aabb(&cube(Point3::new(-50.0, 0.0, -10.0), 1.0)));
+        assert!(!frustum.intersects_aabb(&cube(Point3::new(0.0, 50.0, -10.0), 1.0)));
+        assert!(!frustum.intersects_aabb(&cube(Point3::new(0.0, 0.0, -200.0), 1.0)));
+    }
+
+    #[test]
+    fn aabb_straddling() {
+        let frustum = frustum();
+
+        // Across the near plane, the left plane and the far plane
+        assert!(frustum.intersects_aabb(&cube(Point3::new(0.0, 0.0, -1.0), 0.5)));
+        assert!(frustum.intersects_aabb(&cube(Point3::new(-10.0, 0.0, -10.0), 1.0)));
+        assert!(frustum.intersects_aabb(&cube(Point3::new(0.0, 0.0, -100.0), 1.0)));
+    }
+
+    #[test]
+    fn sphere_inside() {
+        assert!(frustum().intersects_sphere(&BoundingSphere::new(Point3::new(0.0, 0.0, -10.0), 1.0)));
+    }
+
+    #[test]
+    fn sphere_outside() {
+        let frustum = frustum();
+
+        assert!(!frustum.intersects_sphere(&BoundingSphere::new(Point3::new(0.0, 0.0, 5.0), 1.0)));
+        assert!(!frustum.intersects_sphere(&BoundingSphere::new(Point3::new(-50.0, 0.0, -10.0), 1.0)));
+        assert!(!frustum.intersects_sphere(&BoundingSphere::new(Point3::new(0.0, 0.0, -200.0), 1.0)));
+    }
+
+    #[test]
+    fn sphere_straddling() {
+        let frustum = frustum();
+
+        assert!(frustum.intersects_sphere(&BoundingSphere::new(Point3::new(0.0, 0.0, -0.5), 1.0)));
+        assert!(frustum.intersects_sphere(&BoundingSphere::new(Point3::new(-10.5, 0.0, -10.0), 1.0)));
+        assert!(frustum.intersects_sphere(&BoundingSphere::new(Point3::new(0.0, 0.0, -100.5), 1.0)));
+    }
+
+    #[test]
+    fn view_matrix_moves_the_frustum() {
+        // Same camera moved to z = 50, what was in front of it is now behind
+        let projection = Perspective3::new(1.0, 90.0f32.to_radians(), 1.0, 100.0).to_homogeneous();
+        let view = Matrix4::new_translation(&Vector3::new(0.0, 0.0, -50.0));
+        let frustum = Frustum::from_matrix(&(projection * view), false);
+
+        assert!(frustum.intersects_aabb(&cube(Point3::new(0.0, 0.0, 40.0), 1.0)));
+        assert!(!frustum.intersects_aabb(&cube(Point3::new(0.0, 0.0, 55.0), 1.0)));
+    }
+
+    #[test]
+    fn infinite_projection_has_no_far_plane() {
+        let projection = Projection::ReverseZInfinite { fov_deg: 90.0, near: 1.0 }.matrix(1.0);
+        let frustum = Frustum::from_matrix(&projection, true);
+
+        assert_eq!(frustum.planes.len(), 5);
+        assert!(frustum.contains_point(&Point3::new(0.0, 0.0, -1.0e6)));
+        assert!(!frustum.contains_point(&Point3::new(0.0, 0.0, -0.5)));
+    }
+}
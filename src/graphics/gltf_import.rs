@@ -0,0 +1,724 @@
+use std::fmt;
+
+use gl::types::GLuint;
+use image::RgbaImage;
+use nalgebra::{Matrix4, Quaternion, UnitQuaternion, Vector3};
+
+use crate::graphics::animation::{AnimationClip, Channel, Interpolation, Property};
+use crate::graphics::mesh::{Mesh, MeshData, MorphTarget, MAX_MORPH_TARGETS};
+use crate::graphics::resources::Resources;
+use crate::graphics::skin::{Skin, MAX_JOINTS};
+use crate::graphics::texture::{Sampler, Texture};
+
+#[derive(Debug)]
+pub enum Error {
+    Import { name: String, inner: gltf::Error },
+    MissingPositions { mesh: String, primitive: usize },
+    UnsupportedImageFormat { image: usize, format: gltf::image::Format },
+    TooManyJoints { skin: String, joints: usize },
+    TooManyMorphTargets { mesh: String, primitive: usize, targets: usize },
+    /// An attribute or morph target doesn't have one value per vertex
+    AttributeCountMismatch { mesh: String, primitive: usize, attribute: String, count: usize, vertices: usize },
+    IndexOutOfRange { mesh: String, primitive: usize, index: GLuint, vertices: usize },
+    /// Points and lines, the engine only draws triangles
+    UnsupportedMode { mesh: String, primitive: usize, mode: gltf::mesh::Mode },
+    /// A channel's output doesn't have one value (three for cubic splines) per keyframe
+    AnimationValueCount { animation: String, channel: usize, values: usize, expected: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Import { name, inner } => write!(f, "{}: {}", name, inner),
+            Error::MissingPositions { mesh, primitive } => {
+                write!(f, "primitive {} of mesh '{}' has no positions", primitive, mesh)
+            }
+            Error::UnsupportedImageFormat { image, format } => {
+                write!(f, "image {} has unsupported format {:?}", image, format)
+            }
+            Error::TooManyJoints { skin, joints } => {
+                write!(f, "skin '{}' has {} joints, at most {} are supported", skin, joints, MAX_JOINTS)
+            }
+            Error::TooManyMorphTargets { mesh, primitive, targets } => {
+                write!(f, "primitive {} of mesh '{}' has {} morph targets, at most {} are supported",
+                       primitive, mesh, targets, MAX_MORPH_TARGETS)
+            }
+            Error::AttributeCountMismatch { mesh, primitive, attribute, count, vertices } => {
+                write!(f, "primitive {} of mesh '{}' has {} {} for {} vertices", primitive, mesh, count, attribute, vertices)
+            }
+            Error::IndexOutOfRange { mesh, primitive, index, vertices } => {
+                write!(f, "primitive {} of mesh '{}' uses vertex {} of {}", primitive, mesh, index, vertices)
+            }
+            Error::UnsupportedMode { mesh, primitive, mode } => {
+                write!(f, "primitive {} of mesh '{}' is drawn as {:?}, only triangles are supported", primitive, mesh, mode)
+            }
+            Error::AnimationValueCount { animation, channel, values, expected } => {
+                write!(f, "channel {} of animation '{}' has {} values, {} expected", channel, animation, values, expected)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaMode {
+    Opaque,
+    Mask,
+    Blend,
+}
+
+/// Metallic-roughness material, texture fields index `GltfScene::textures`
+#[derive(Debug, Clone)]
+pub struct PbrMaterial {
+    pub name: String,
+    pub base_color_factor: [f32; 4],
+    pub base_color_texture: Option<usize>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub metallic_roughness_texture: Option<usize>,
+    pub normal_texture: Option<usize>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<usize>,
+    pub occlusion_strength: f32,
+    pub emissive_texture: Option<usize>,
+    pub emissive_factor: [f32; 3],
+    pub alpha_mode: AlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool,
+}
+
+/// Decoded texture image with the sampler it is meant to be used with
+pub struct TextureData {
+    pub name: String,
+    pub image: RgbaImage,
+    pub sampler: Sampler,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CameraProjection {
+    Perspective { yfov: f32, aspect_ratio: Option<f32>, znear: f32, zfar: Option<f32> },
+    Orthographic { xmag: f32, ymag: f32, znear: f32, zfar: f32 },
+}
+
+#[derive(Debug, Clone)]
+pub struct CameraData {
+    pub name: String,
+    pub projection: CameraProjection,
+}
+
+#[derive(Debug, Clone)]
+pub struct Primitive {
+    pub data: MeshData,
+    pub material: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MeshGroup {
+    pub name: String,
+    pub primitives: Vec<Primitive>,
+    /// Default morph target weights
+    pub weights: Vec<f32>,
+}
+
+/// Node of the imported hierarchy, indices refer to the `GltfScene` lists
+#[derive(Debug, Clone)]
+pub struct NodeData {
+    pub name: String,
+    pub translation: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub scale: Vector3<f32>,
+    pub children: Vec<usize>,
+    pub mesh: Option<usize>,
+    pub camera: Option<usize>,
+    pub skin: Option<usize>,
+    /// Morph target weights overriding the mesh ones
+    pub weights: Option<Vec<f32>>,
+}
+
+/// Everything read from a .gltf or .glb file
+pub struct GltfScene {
+    pub meshes: Vec<MeshGroup>,
+    pub materials: Vec<PbrMaterial>,
+    pub textures: Vec<TextureData>,
+    pub cameras: Vec<CameraData>,
+    pub nodes: Vec<NodeData>,
+    /// Root nodes of every scene of the file
+    pub scenes: Vec<Vec<usize>>,
+    /// Scene to show when the file doesn't say
+    pub default_scene: Option<usize>,
+    pub animations: Vec<AnimationClip>,
+    pub skins: Vec<Skin>,
+}
+
+impl GltfScene {
+    /// Imports a .gltf (with embedded or external buffers) or a .glb resource
+    pub fn from_res(res: &Resources, name: &str) -> Result<GltfScene, Error> {
+        let (document, buffers, images) = gltf::import(res.path(name))
+            .map_err(|inner| Error::Import { name: String::from(name), inner })?;
+
+        GltfScene::convert(&document, &buffers, &images)
+    }
+
+    fn convert(document: &gltf::Document, buffers: &[gltf::buffer::Data], images: &[gltf::image::Data]) -> Result<GltfScene, Error> {
+        let mut textures = Vec::new();
+        for texture in document.textures() {
+            let image_index = texture.source().index();
+            let image = convert_image(&images[image_index], image_index)?;
+
+            textures.push(TextureData {
+                name: texture.name().map(String::from).unwrap_or_else(|| format!("texture_{}", texture.index())),
+                image,
+                sampler: convert_sampler(&texture.sampler()),
+            });
+        }
+
+        let materials = document.materials()
+            .map(|material| convert_material(&material))
+            .collect();
+
+        let mut meshes = Vec::new();
+        for mesh in document.meshes() {
+            meshes.push(convert_mesh(&mesh, buffers)?);
+        }
+
+        let cameras = document.cameras()
+            .map(|camera| CameraData {
+                name: camera.name().map(String::from).unwrap_or_else(|| format!("camera_{}", camera.index())),
+                projection: match camera.projection() {
+                    gltf::camera::Projection::Perspective(perspective) => CameraProjection::Perspective {
+                        yfov: perspective.yfov(),
+                        aspect_ratio: perspective.aspect_ratio(),
+                        znear: perspective.znear(),
+                        zfar: perspective.zfar(),
+                    },
+                    gltf::camera::Projection::Orthographic(orthographic) => CameraProjection::Orthographic {
+                        xmag: orthographic.xmag(),
+                        ymag: orthographic.ymag(),
+                        znear: orthographic.znear(),
+                        zfar: orthographic.zfar(),
+                    },
+                },
+            })
+            .collect();
+
+        let nodes = document.nodes()
+            .map(|node| {
+                let (translation, rotation, scale) = node.transform().decomposed();
+
+                NodeData {
+                    name: node.name().map(String::from).unwrap_or_else(|| format!("node_{}", node.index())),
+                    translation: Vector3::from(translation),
+                    // glTF stores quaternions as x, y, z, w
+                    rotation: UnitQuaternion::from_quaternion(
+                        Quaternion::new(rotation[3], rotation[0], rotation[1], rotation[2])
+                    ),
+                    scale: Vector3::from(scale),
+                    children: node.children().map(|child| child.index()).collect(),
+                    mesh: node.mesh().map(|mesh| mesh.index()),
+                    camera: node.camera().map(|camera| camera.index()),
+                    skin: node.skin().map(|skin| skin.index()),
+                    weights: node.weights().map(|weights| weights.to_vec()),
+                }
+            })
+            .collect();
+
+        let scenes = document.scenes()
+            .map(|scene| scene.nodes().map(|node| node.index()).collect())
+            .collect();
+
+        let animations = document.animations()
+            .map(|animation| convert_animation(&animation, buffers))
+            .collect::<Result<_, _>>()?;
+
+        let mut skins = Vec::new();
+        for skin in document.skins() {
+            let name = skin.name().map(String::from).unwrap_or_else(|| format!("skin_{}", skin.index()));
+            let joints: Vec<usize> = skin.joints().map(|joint| joint.index()).collect();
+
+            // The joint matrices have to fit in the shader's uniform block
+            if joints.len() > MAX_JOINTS {
+                return Err(Error::TooManyJoints { skin: name, joints: joints.len() });
+            }
+
+            // Without inverse bind matrices the joints are already in bind pose
+            let inverse_bind_matrices = skin.reader(|buffer| Some(&buffers[buffer.index()]))
+                .read_inverse_bind_matrices()
+                .map(|matrices| matrices.map(Matrix4::from).collect())
+                .unwrap_or_else(|| vec![Matrix4::identity(); joints.len()]);
+
+            skins.push(Skin {
+                name,
+                joints,
+                inverse_bind_matrices,
+            });
+        }
+
+        Ok(GltfScene {
+            meshes,
+            materials,
+            textures,
+            cameras,
+            nodes,
+            scenes,
+            default_scene: document.default_scene().map(|scene| scene.index()),
+            animations,
+            skins,
+        })
+    }
+
+    /// Uploads every primitive, grouped the same way as `meshes`
+    pub fn upload_meshes(&self, gl: &gl::Gl) -> Vec<Vec<Mesh>> {
+        self.meshes.iter()
+            .map(|group| {
+                group.primitives.iter().enumerate().map(|(index, primitive)| {
+                    let mesh = Mesh::new(gl, &primitive.data);
+                    mesh.set_label(&format!("{} primitive {}", group.name, index));
+                    mesh
+                }).collect()
+            })
+            .collect()
+    }
+
+    pub fn upload_textures(&self, gl: &gl::Gl) -> Vec<Texture> {
+        self.textures.iter()
+            .map(|texture| {
+                let uploaded = Texture::with_sampler(gl, &texture.image, gl::TEXTURE_2D, &texture.sampler);
+                uploaded.set_label(&texture.name);
+                uploaded
+            })
+            .collect()
+    }
+}
+
+fn convert_image(data: &gltf::image::Data, index: usize) -> Result<RgbaImage, Error> {
+    use gltf::image::Format;
+
+    let channels = match data.format {
+        Format::R8 => 1,
+        Format::R8G8 => 2,
+        Format::R8G8B8 => 3,
+        Format::R8G8B8A8 => 4,
+        Format::R16 => 1,
+        Format::R16G16 => 2,
+        Format::R16G16B16 => 3,
+        Format::R16G16B16A16 => 4,
+        format => return Err(Error::UnsupportedImageFormat { image: index, format }),
+    };
+
+    // 16 bit images keep only their most significant byte (little endian)
+    let bytes_per_channel = match data.format {
+        Format::R16 | Format::R16G16 | Format::R16G16B16 | Format::R16G16B16A16 => 2,
+        _ => 1,
+    };
+
+    let pixel_count = (data.width * data.height) as usize;
+    let mut rgba: Vec<u8> = Vec::with_capacity(pixel_count * 4);
+
+    for pixel in data.pixels.chunks(channels * bytes_per_channel).take(pixel_count) {
+        let channel = |i: usize| pixel[i * bytes_per_channel + bytes_per_channel - 1];
+
+        match channels {
+            1 => rgba.extend_from_slice(&[channel(0), channel(0), channel(0), 255]),
+            2 => rgba.extend_from_slice(&[channel(0), channel(1), 0, 255]),
+            3 => rgba.extend_from_slice(&[channel(0), channel(1), channel(2), 255]),
+            _ => rgba.extend_from_slice(&[channel(0), channel(1), channel(2), channel(3)]),
+        }
+    }
+
+    RgbaImage::from_raw(data.width, data.height, rgba)
+        .ok_or(Error::UnsupportedImageFormat { image: index, format: data.format })
+}
+
+fn convert_sampler(sampler: &gltf::texture::Sampler) -> Sampler {
+    let default = Sampler::default();
+
+    Sampler {
+        min_filter: sampler.min_filter().map(|filter| filter.as_gl_enum()).unwrap_or(gl::LINEAR_MIPMAP_LINEAR),
+        mag_filter: sampler.mag_filter().map(|filter| filter.as_gl_enum()).unwrap_or(default.mag_filter),
+        wrap_s: sampler.wrap_s().as_gl_enum(),
+        wrap_t: sampler.wrap_t().as_gl_enum(),
+    }
+}
+
+fn convert_material(material: &gltf::Material) -> PbrMaterial {
+    let pbr = material.pbr_metallic_roughness();
+
+    PbrMaterial {
+        name: material.name().map(String::from)
+            .unwrap_or_else(|| format!("material_{}", material.index().unwrap_or(0))),
+        base_color_factor: pbr.base_color_factor(),
+        base_color_texture: pbr.base_color_texture().map(|info| info.texture().index()),
+        metallic_factor: pbr.metallic_factor(),
+        roughness_factor: pbr.roughness_factor(),
+        metallic_roughness_texture: pbr.metallic_roughness_texture().map(|info| info.texture().index()),
+        normal_texture: material.normal_texture().map(|normal| normal.texture().index()),
+        normal_scale: material.normal_texture().map(|normal| normal.scale()).unwrap_or(1.0),
+        occlusion_texture: material.occlusion_texture().map(|occlusion| occlusion.texture().index()),
+        occlusion_strength: material.occlusion_texture().map(|occlusion| occlusion.strength()).unwrap_or(1.0),
+        emissive_texture: material.emissive_texture().map(|info| info.texture().index()),
+        emissive_factor: material.emissive_factor(),
+        alpha_mode: match material.alpha_mode() {
+            gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+            gltf::material::AlphaMode::Mask => AlphaMode::Mask,
+            gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+        },
+        alpha_cutoff: material.alpha_cutoff().unwrap_or(0.5),
+        double_sided: material.double_sided(),
+    }
+}
+
+fn convert_mesh(mesh: &gltf::Mesh, buffers: &[gltf::buffer::Data]) -> Result<MeshGroup, Error> {
+    let name = mesh.name().map(String::from).unwrap_or_else(|| format!("mesh_{}", mesh.index()));
+    let mut primitives = Vec::new();
+
+    for primitive in mesh.primitives() {
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+        let positions: Vec<[f32; 3]> = reader.read_positions()
+            .ok_or_else(|| Error::MissingPositions { mesh: name.clone(), primitive: primitive.index() })?
+            .collect();
+        let normals: Option<Vec<[f32; 3]>> = reader.read_normals().map(|normals| normals.collect());
+        let tex_coords: Option<Vec<[f32; 2]>> = reader.read_tex_coords(0)
+            .map(|tex_coords| tex_coords.into_f32().collect());
+
+        let vertices = positions.len();
+        let check_count = |attribute: &str, count: usize| {
+            if count == vertices {
+                Ok(())
+            } else {
+                Err(Error::AttributeCountMismatch {
+                    mesh: name.clone(),
+                    primitive: primitive.index(),
+                    attribute: String::from(attribute),
+                    count,
+                    vertices,
+                })
+            }
+        };
+        if let Some(normals) = &normals {
+            check_count("normals", normals.len())?;
+        }
+        if let Some(tex_coords) = &tex_coords {
+            check_count("texture coordinates", tex_coords.len())?;
+        }
+
+        let mut data = MeshData::new();
+        for (index, position) in positions.iter().enumerate() {
+            data.push_vertex(
+                *position,
+                normals.as_ref().map(|normals| normals[index]).unwrap_or([0.0, 0.0, 0.0]),
+                tex_coords.as_ref().map(|tex_coords| tex_coords[index]).unwrap_or([0.0, 0.0]),
+            );
+        }
+
+        let indices: Vec<GLuint> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..positions.len() as GLuint).collect(),
+        };
+
+        if let Some(&index) = indices.iter().find(|&&index| index as usize >= vertices) {
+            return Err(Error::IndexOutOfRange { mesh: name.clone(), primitive: primitive.index(), index, vertices });
+        }
+
+        data.indices = match primitive.mode() {
+            gltf::mesh::Mode::Triangles => indices,
+            gltf::mesh::Mode::TriangleStrip => {
+                let mut triangles = Vec::new();
+                for i in 2..indices.len() {
+                    // Every other triangle has its winding flipped
+                    if i % 2 == 0 {
+                        triangles.extend_from_slice(&[indices[i - 2], indices[i - 1], indices[i]]);
+                    } else {
+                        triangles.extend_from_slice(&[indices[i - 1], indices[i - 2], indices[i]]);
+                    }
+                }
+                triangles
+            }
+            gltf::mesh::Mode::TriangleFan => {
+                let mut triangles = Vec::new();
+                for i in 2..indices.len() {
+                    triangles.extend_from_slice(&[indices[0], indices[i - 1], indices[i]]);
+                }
+                triangles
+            }
+            mode => return Err(Error::UnsupportedMode { mesh: name.clone(), primitive: primitive.index(), mode }),
+        };
+
+        if normals.is_none() {
+            data.generate_normals(&vec![true; positions.len()]);
+        }
+
+        if let (Some(joints), Some(weights)) = (reader.read_joints(0), reader.read_weights(0)) {
+            data.joints = joints.into_u16().collect();
+            check_count("joints", data.joints.len())?;
+            data.weights = weights.into_f32()
+                .map(|weights| {
+                    // The shader expects the weights to add up to one
+                    let sum: f32 = weights.iter().sum();
+                    if sum > 0.0 {
+                        [weights[0] / sum, weights[1] / sum, weights[2] / sum, weights[3] / sum]
+                    } else {
+                        [1.0, 0.0, 0.0, 0.0]
+                    }
+                })
+                .collect();
+            check_count("weights", data.weights.len())?;
+        }
+
+        data.morph_targets = reader.read_morph_targets()
+            .map(|(positions, normals, _)| MorphTarget {
+                positions: positions.map(|positions| positions.collect())
+                    .unwrap_or_else(|| vec![[0.0, 0.0, 0.0]; data.vertex_count()]),
+                normals: normals.map(|normals| normals.collect()).unwrap_or_default(),
+            })
+            .collect();
+
+        for (target, morph_target) in data.morph_targets.iter().enumerate() {
+            check_count(&format!("positions in morph target {}", target), morph_target.positions.len())?;
+            if !morph_target.normals.is_empty() {
+                check_count(&format!("normals in morph target {}", target), morph_target.normals.len())?;
+            }
+        }
+
+        // The shaders only blend a fixed number of targets
+        if data.morph_targets.len() > MAX_MORPH_TARGETS {
+            return Err(Error::TooManyMorphTargets {
+                mesh: name.clone(),
+                primitive: primitive.index(),
+                targets: data.morph_targets.len(),
+            });
+        }
+
+        primitives.push(Primitive {
+            data,
+            material: primitive.material().index(),
+        });
+    }
+
+    Ok(MeshGroup {
+        name,
+        primitives,
+        weights: mesh.weights().map(|weights| weights.to_vec()).unwrap_or_default(),
+    })
+}
+
+fn convert_animation(animation: &gltf::Animation, buffers: &[gltf::buffer::Data]) -> Result<AnimationClip, Error> {
+    use gltf::animation::util::ReadOutputs;
+
+    let name = animation.name().map(String::from).unwrap_or_else(|| format!("animation_{}", animation.index()));
+    let mut channels = Vec::new();
+
+    for channel in animation.channels() {
+        let reader = channel.reader(|buffer| Some(&buffers[buffer.index()]));
+
+        let times: Vec<f32> = match reader.read_inputs() {
+            Some(inputs) => inputs.collect(),
+            None => continue,
+        };
+
+        let interpolation = match channel.sampler().interpolation() {
+            gltf::animation::Interpolation::Linear => Interpolation::Linear,
+            gltf::animation::Interpolation::Step => Interpolation::Step,
+            gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+        };
+        let values_per_key = match interpolation {
+            Interpolation::CubicSpline => times.len() * 3,
+            _ => times.len(),
+        };
+
+        let (property, values): (Property, Vec<f32>) = match reader.read_outputs() {
+            Some(ReadOutputs::Translations(translations)) => {
+                (Property::Translation, translations.flatten().collect())
+            }
+            Some(ReadOutputs::Rotations(rotations)) => {
+                (Property::Rotation, rotations.into_f32().flatten().collect())
+            }
+            Some(ReadOutputs::Scales(scales)) => {
+                (Property::Scale, scales.flatten().collect())
+            }
+            Some(ReadOutputs::MorphTargetWeights(weights)) => {
+                let weights: Vec<f32> = weights.into_f32().collect();
+                (Property::MorphWeights(weights.len() / values_per_key.max(1)), weights)
+            }
+            None => continue,
+        };
+
+        // Sampling slices the values by keyframe, they have to line up with the times
+        let expected = values_per_key * property.components();
+        if values.len() != expected {
+            return Err(Error::AnimationValueCount {
+                animation: name,
+                channel: channel.index(),
+                values: values.len(),
+                expected,
+            });
+        }
+
+        channels.push(Channel {
+            node: channel.target().node().index(),
+            property,
+            interpolation,
+            times,
+            values,
+        });
+    }
+
+    Ok(AnimationClip::new(&name, channels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, GltfScene};
+
+    /// Three positions, two normals and the indices 0, 1, 5
+    fn buffer() -> Vec<u8> {
+        let floats = [
+            0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 1.0, 0.0, 0.0, 1.0,
+        ];
+
+        let mut bytes: Vec<u8> = floats.iter().flat_map(|value| value.to_le_bytes()).collect();
+        bytes.extend([0u16, 1, 5, 0].iter().flat_map(|value| value.to_le_bytes()));
+        bytes
+    }
+
+    fn base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+        let mut encoded = String::new();
+        for chunk in bytes.chunks(3) {
+            let value = (chunk[0] as u32) << 16
+                | (chunk.get(1).copied().unwrap_or(0) as u32) << 8
+                | chunk.get(2).copied().unwrap_or(0) as u32;
+
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    encoded.push(ALPHABET[(value >> (18 - 6 * i) & 63) as usize] as char);
+                } else {
+                    encoded.push('=');
+                }
+            }
+        }
+        encoded
+    }
+
+    /// A document with one mesh, `attributes` and `extra` are spliced into its primitive
+    /// and `animations` into the document
+    /// Node 0 is the scene root with node 1 as its child, node 2 is outside the scene
+    /// Accessors 3 and 4 hold the times 0, 0 and 0, 0, 1
+    fn import(attributes: &str, extra: &str, animations: &str) -> Result<GltfScene, Error> {
+        let buffer = buffer();
+        let json = format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "buffers": [{{ "byteLength": {}, "uri": "data:application/octet-stream;base64,{}" }}],
+                "bufferViews": [
+                    {{ "buffer": 0, "byteOffset": 0, "byteLength": 36 }},
+                    {{ "buffer": 0, "byteOffset": 36, "byteLength": 24 }},
+                    {{ "buffer": 0, "byteOffset": 60, "byteLength": 6 }}
+                ],
+                "accessors": [
+                    {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0] }},
+                    {{ "bufferView": 1, "componentType": 5126, "count": 2, "type": "VEC3" }},
+                    {{ "bufferView": 2, "componentType": 5123, "count": 3, "type": "SCALAR" }},
+                    {{ "bufferView": 1, "componentType": 5126, "count": 2, "type": "SCALAR" }},
+                    {{ "bufferView": 1, "componentType": 5126, "count": 3, "type": "SCALAR" }}
+                ],
+                "meshes": [{{ "name": "triangle", "primitives": [{{ "attributes": {{ "POSITION": 0 {} }} {} }}] }}],
+                "nodes": [
+                    {{ "name": "root", "children": [1] }},
+                    {{ "name": "child", "mesh": 0, "translation": [1, 2, 3] }},
+                    {{ "name": "unused" }}
+                ],
+                "scenes": [{{ "nodes": [0] }}],
+                "scene": 0
+                {}
+            }}"#,
+            buffer.len(), base64(&buffer), attributes, extra, animations
+        );
+
+        let (document, buffers, images) = gltf::import_slice(json.as_bytes())
+            .map_err(|inner| Error::Import { name: String::from("test.gltf"), inner })?;
+
+        GltfScene::convert(&document, &buffers, &images)
+    }
+
+    #[test]
+    fn imports_meshes_and_nodes() {
+        let scene = import("", "", "").unwrap();
+        let data = &scene.meshes[0].primitives[0].data;
+
+        assert_eq!(scene.meshes[0].name, "triangle");
+        assert_eq!(data.vertex_count(), 3);
+        assert_eq!(data.indices, vec![0, 1, 2]);
+        // Normals generated from the winding
+        assert_eq!(&data.vertices[3..6], &[0.0, 0.0, 1.0]);
+
+        assert_eq!(scene.nodes.len(), 3);
+        assert_eq!(scene.nodes[0].children, vec![1]);
+        assert_eq!(scene.nodes[1].mesh, Some(0));
+        assert_eq!(scene.nodes[1].translation.z, 3.0);
+        assert_eq!(scene.scenes, vec![vec![0]]);
+        assert_eq!(scene.default_scene, Some(0));
+    }
+
+    #[test]
+    fn attribute_count_mismatch() {
+        match import(r#", "NORMAL": 1"#, "", "") {
+            Err(Error::AttributeCountMismatch { attribute, count: 2, vertices: 3, .. }) => assert_eq!(attribute, "normals"),
+            other => panic!("unexpected result {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn index_out_of_range() {
+        assert!(matches!(
+            import("", r#", "indices": 2"#, ""),
+            Err(Error::IndexOutOfRange { index: 5, vertices: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn lines_are_rejected() {
+        assert!(matches!(
+            import("", r#", "mode": 1"#, ""),
+            Err(Error::UnsupportedMode { mode: gltf::mesh::Mode::Lines, .. })
+        ));
+    }
+
+    /// Moves node 1 with the positions as translations, `input` picks the keyframe times
+    fn translation_animation(input: usize, interpolation: &str) -> String {
+        format!(
+            r#", "animations": [{{
+                "name": "move",
+                "channels": [{{ "sampler": 0, "target": {{ "node": 1, "path": "translation" }} }}],
+                "samplers": [{{ "input": {}, "output": 0, "interpolation": "{}" }}]
+            }}]"#,
+            input, interpolation
+        )
+    }
+
+    #[test]
+    fn imports_animations() {
+        let scene = import("", "", &translation_animation(4, "LINEAR")).unwrap();
+        let channel = &scene.animations[0].channels[0];
+
+        assert_eq!(scene.animations[0].name, "move");
+        assert_eq!(scene.animations[0].duration, 1.0);
+        assert_eq!(channel.node, 1);
+        assert_eq!(channel.times, vec![0.0, 0.0, 1.0]);
+        assert_eq!(channel.values.len(), 9);
+    }
+
+    #[test]
+    fn animation_value_count() {
+        assert!(matches!(
+            import("", "", &translation_animation(3, "LINEAR")),
+            Err(Error::AnimationValueCount { channel: 0, values: 9, expected: 6, .. })
+        ));
+        // Cubic splines need an in-tangent, the value and an out-tangent per keyframe
+        assert!(matches!(
+            import("", "", &translation_animation(4, "CUBICSPLINE")),
+            Err(Error::AnimationValueCount { values: 9, expected: 27, .. })
+        ));
+    }
+}
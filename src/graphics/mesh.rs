@@ -0,0 +1,289 @@
+use gl::types::{GLint, GLsizei, GLuint};
+use nalgebra::Point3;
+
+use crate::graphics::bounds::Aabb;
+use crate::graphics::ebo::EBO;
+use crate::graphics::profiler;
+use crate::graphics::vao::VAO;
+use crate::graphics::vbo::VBO;
+
+/// Number of floats of a single vertex: position(3) normal(3) texture(2)
+pub const FLOATS_PER_VERTEX: usize = 8;
+
+/// Morph targets uploaded per mesh, must match the shaders' `morphWeights` size
+pub const MAX_MORPH_TARGETS: usize = 4;
+
+/// Offsets added to the base vertices, scaled by the target's weight
+#[derive(Debug, Clone, Default)]
+pub struct MorphTarget {
+    pub positions: Vec<[f32; 3]>,
+    /// Empty when the target doesn't change the normals
+    pub normals: Vec<[f32; 3]>,
+}
+
+/// Indexed vertex data living on the CPU side
+/// The vertices are interleaved following the layout of FLOATS_PER_VERTEX
+#[derive(Debug, Clone, Default)]
+pub struct MeshData {
+    pub vertices: Vec<f32>,
+    pub indices: Vec<GLuint>,
+
+    /// Up to four joints per vertex with their weights, empty if the mesh isn't skinned
+    pub joints: Vec<[u16; 4]>,
+    pub weights: Vec<[f32; 4]>,
+
+    /// At most MAX_MORPH_TARGETS, `Mesh::new` only uploads that many
+    pub morph_targets: Vec<MorphTarget>,
+}
+
+impl MeshData {
+    pub fn new() -> MeshData {
+        MeshData::default()
+    }
+
+    /// Appends a vertex and returns its index
+    pub fn push_vertex(&mut self, position: [f32; 3], normal: [f32; 3], tex_coords: [f32; 2]) -> GLuint {
+        let index = self.vertex_count() as GLuint;
+
+        self.vertices.extend_from_slice(&position);
+        self.vertices.extend_from_slice(&normal);
+        self.vertices.extend_from_slice(&tex_coords);
+
+        index
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len() / FLOATS_PER_VERTEX
+    }
+
+    pub fn is_skinned(&self) -> bool {
+        !self.joints.is_empty()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    pub fn position(&self, index: usize) -> [f32; 3] {
+        let start = index * FLOATS_PER_VERTEX;
+        [self.vertices[start], self.vertices[start + 1], self.vertices[start + 2]]
+    }
+
+    /// Bounds of the vertices, including the ones displaced by fully weighted morph targets
+    pub fn bounds(&self) -> Option<Aabb> {
+        let base = (0..self.vertex_count()).map(|index| Point3::from(self.position(index)));
+
+        let morphed = self.morph_targets.iter().flat_map(|target| {
+            target.positions.iter().enumerate().map(move |(index, delta)| {
+                let position = self.position(index);
+                Point3::new(position[0] + delta[0], position[1] + delta[1], position[2] + delta[2])
+            })
+        });
+
+        Aabb::from_points(base.chain(morphed))
+    }
+
+    /// Smooths normals for the vertices flagged as missing one
+    /// by averaging the normals of the triangles that share them
+    pub fn generate_normals(&mut self, missing: &[bool]) {
+        if !missing.iter().any(|&missing| missing) {
+            return;
+        }
+
+        let mut normals = vec![[0.0f32; 3]; self.vertex_count()];
+
+        for triangle in self.indices.chunks(3) {
+            let a = self.position(triangle[0] as usize);
+            let b = self.position(triangle[1] as usize);
+            let c = self.position(triangle[2] as usize);
+
+            let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+            let ac = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+            let face_normal = [
+                ab[1] * ac[2] - ab[2] * ac[1],
+                ab[2] * ac[0] - ab[0] * ac[2],
+                ab[0] * ac[1] - ab[1] * ac[0],
+            ];
+
+            for &index in triangle {
+                for axis in 0..3 {
+                    normals[index as usize][axis] += face_normal[axis];
+                }
+            }
+        }
+
+        for (index, normal) in normals.iter().enumerate() {
+            if !missing[index] {
+                continue;
+            }
+
+            let length = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+            if length > 0.0 {
+                let start = index * FLOATS_PER_VERTEX + 3;
+                for (axis, value) in normal.iter().enumerate() {
+                    self.vertices[start + axis] = value / length;
+                }
+            }
+        }
+    }
+}
+
+/// Mesh uploaded to the GPU, ready to be drawn
+pub struct Mesh {
+    gl: gl::Gl,
+    pub vao: VAO,
+    vbo: VBO,
+    ebo: EBO,
+    /// Joints and weights streams of skinned meshes
+    skin_vbos: Option<(VBO, VBO)>,
+    /// One position/normal delta stream per morph target
+    morph_vbos: Vec<VBO>,
+    index_count: GLsizei,
+    /// Local space bounds, `None` for empty meshes
+    pub bounds: Option<Aabb>,
+}
+
+impl Mesh {
+    pub fn new(gl: &gl::Gl, data: &MeshData) -> Mesh {
+        let vao = VAO::new(gl);
+        vao.bind();
+
+        let vbo = VBO::new(gl, &data.vertices);
+        let ebo = EBO::new(gl, &data.indices);
+
+        let stride = (FLOATS_PER_VERTEX * std::mem::size_of::<f32>()) as GLint;
+
+        // Positions
+        vao.link_attrib(&vbo, 0, 3, stride, 0);
+
+        // Normals
+        vao.link_attrib(
+            &vbo,
+            1,
+            3,
+            stride,
+            3 * std::mem::size_of::<f32>()
+        );
+
+        // Texture coordinates
+        vao.link_attrib(
+            &vbo,
+            2,
+            2,
+            stride,
+            6 * std::mem::size_of::<f32>()
+        );
+
+        let skin_vbos = if data.is_skinned() {
+            let joints_vbo = VBO::from_slice(gl, &data.joints);
+            let weights_vbo = VBO::from_slice(gl, &data.weights);
+
+            // Joint indices
+            vao.link_attrib_i(&joints_vbo, 3, 4, gl::UNSIGNED_SHORT, 0, 0);
+
+            // Joint weights
+            vao.link_attrib(&weights_vbo, 4, 4, 0, 0);
+
+            Some((joints_vbo, weights_vbo))
+        } else {
+            None
+        };
+
+        // The importer refuses meshes with more targets than the shader has attributes for
+        debug_assert!(data.morph_targets.len() <= MAX_MORPH_TARGETS, "too many morph targets");
+
+        let morph_vbos: Vec<VBO> = data.morph_targets.iter()
+            .take(MAX_MORPH_TARGETS)
+            .enumerate()
+            .map(|(index, target)| {
+                // Interleaved position and normal deltas
+                let mut deltas: Vec<f32> = Vec::with_capacity(target.positions.len() * 6);
+                for (vertex, position) in target.positions.iter().enumerate() {
+                    deltas.extend_from_slice(position);
+                    deltas.extend_from_slice(&target.normals.get(vertex).copied().unwrap_or([0.0, 0.0, 0.0]));
+                }
+
+                let morph_vbo = VBO::new(gl, &deltas);
+                let morph_stride = (6 * std::mem::size_of::<f32>()) as GLint;
+
+                // Position deltas
+                vao.link_attrib(&morph_vbo, 5 + index as GLuint, 3, morph_stride, 0);
+
+                // Normal deltas
+                vao.link_attrib(
+                    &morph_vbo,
+                    5 + MAX_MORPH_TARGETS as GLuint + index as GLuint,
+                    3,
+                    morph_stride,
+                    3 * std::mem::size_of::<f32>()
+                );
+
+                morph_vbo
+            })
+            .collect();
+
+        vao.unbind();
+        vbo.unbind();
+        ebo.unbind();
+
+        Mesh {
+            gl: gl.clone(),
+            vao,
+            vbo,
+            ebo,
+            skin_vbos,
+            morph_vbos,
+            index_count: data.indices.len() as GLsizei,
+            bounds: data.bounds(),
+        }
+    }
+
+    pub fn morph_target_count(&self) -> usize {
+        self.morph_vbos.len()
+    }
+
+    pub fn draw(&self) {
+        self.vao.bind();
+        unsafe {
+            self.gl.DrawElements(
+                gl::TRIANGLES,
+                self.index_count,
+                gl::UNSIGNED_INT,
+                std::ptr::null()
+            );
+        }
+
+        profiler::record_draw_call(self.index_count as u64 / 3);
+    }
+
+    /// Labels the GL objects of the mesh for the debug messages and graphics debuggers
+    pub fn set_label(&self, label: &str) {
+        self.vao.set_label(label);
+        self.vbo.set_label(&format!("{} vertices", label));
+        self.ebo.set_label(&format!("{} indices", label));
+
+        if let Some((joints_vbo, weights_vbo)) = &self.skin_vbos {
+            joints_vbo.set_label(&format!("{} joints", label));
+            weights_vbo.set_label(&format!("{} weights", label));
+        }
+
+        for (index, morph_vbo) in self.morph_vbos.iter().enumerate() {
+            morph_vbo.set_label(&format!("{} morph target {}", label, index));
+        }
+    }
+
+    pub fn delete(&self) {
+        self.vao.delete();
+        self.vbo.delete();
+        self.ebo.delete();
+
+        if let Some((joints_vbo, weights_vbo)) = &self.skin_vbos {
+            joints_vbo.delete();
+            weights_vbo.delete();
+        }
+
+        for morph_vbo in &self.morph_vbos {
+            morph_vbo.delete();
+        }
+    }
+}
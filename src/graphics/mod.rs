@@ -9,6 +9,23 @@ pub mod vao;
 pub mod ebo;
 pub mod texture;
 pub mod camera;
+pub mod camera_controller;
+pub mod camera_path;
+pub mod framebuffer;
+pub mod bounds;
+pub mod frustum;
+pub mod ray;
+pub mod mesh;
+pub mod obj;
+pub mod animation;
+pub mod gltf_import;
+pub mod scene;
+pub mod ubo;
+pub mod skin;
+pub mod query;
+pub mod profiler;
+pub mod debug;
+pub mod render_state;
 
 /// Given a source of shader and the type returns the shader ID
 fn shader_from_source(gl : &gl::Gl, source : &CStr, kind : gl::types::GLuint) -> Result<gl::types::GLuint, String> {
@@ -54,7 +71,7 @@ fn shader_from_source(gl : &gl::Gl, source : &CStr, kind : gl::types::GLuint) ->
     }
 
     // Returns the id of the shader
-    return Ok(id)
+    Ok(id)
 }
 
 /// Creates a buffer of type Vec<u8> filled with b' ' of the length given
@@ -0,0 +1,596 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use gl::types::GLuint;
+
+use crate::graphics::mesh::MeshData;
+use crate::graphics::resources::{self, relative_resource_name, Resources};
+
+/// Material described by a .mtl file
+/// Texture names are already resolved relative to the .obj file, as resource names
+#[derive(Debug, Clone)]
+pub struct Material {
+    pub name: String,
+    pub ambient: [f32; 3],
+    pub diffuse: [f32; 3],
+    pub specular: [f32; 3],
+    pub shininess: f32,
+    pub dissolve: f32,
+    pub diffuse_texture: Option<String>,
+    pub specular_texture: Option<String>,
+    pub normal_texture: Option<String>,
+}
+
+impl Material {
+    fn new(name: &str) -> Material {
+        Material {
+            name: String::from(name),
+            ambient: [0.0, 0.0, 0.0],
+            diffuse: [1.0, 1.0, 1.0],
+            specular: [0.0, 0.0, 0.0],
+            shininess: 0.0,
+            dissolve: 1.0,
+            diffuse_texture: None,
+            specular_texture: None,
+            normal_texture: None,
+        }
+    }
+}
+
+/// Part of an object that is drawn with a single material
+#[derive(Debug, Clone)]
+pub struct ObjMesh {
+    pub group: String,
+    pub material: Option<usize>,
+    pub data: MeshData,
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjObject {
+    pub name: String,
+    pub meshes: Vec<ObjMesh>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjModel {
+    pub objects: Vec<ObjObject>,
+    pub materials: Vec<Material>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Resource { name: String, inner: resources::Error },
+    Parse { file: String, line: usize, message: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Resource { name, inner } => write!(f, "{}: {:?}", name, inner),
+            Error::Parse { file, line, message } => write!(f, "{}:{}: {}", file, line, message),
+        }
+    }
+}
+
+impl ObjModel {
+    /// Loads a .obj resource together with every .mtl file it references
+    pub fn from_res(res: &Resources, name: &str) -> Result<ObjModel, Error> {
+        let source = res.load_string(name)
+            .map_err(|inner| Error::Resource { name: String::from(name), inner })?;
+
+        ObjModel::parse(&source, name, |library_name| res.load_string(library_name))
+    }
+
+    /// Parses a .obj source, `name` is its resource name, `load_library` loads the .mtl resources it references
+    pub fn parse<F>(source: &str, name: &str, mut load_library: F) -> Result<ObjModel, Error>
+        where F: FnMut(&str) -> Result<String, resources::Error>
+    {
+        let mut parser = ObjParser::new(name);
+
+        for (index, line) in source.lines().enumerate() {
+            parser.parse_line(line, index + 1)?;
+        }
+
+        let mut materials: Vec<Material> = Vec::new();
+        for (library, line) in &parser.material_libraries {
+            let library_name = relative_resource_name(name, library);
+            let library_source = load_library(&library_name)
+                .map_err(|inner| Error::Parse {
+                    file: String::from(name),
+                    line: *line,
+                    message: format!("can not load material library {}: {:?}", library_name, inner),
+                })?;
+
+            materials.extend(parse_mtl(&library_source, &library_name, name)?);
+        }
+
+        parser.finish(&materials)
+    }
+}
+
+/// Indices of a face vertex, already converted to 0 based indices
+type VertexKey = (usize, Option<usize>, Option<usize>);
+
+struct MeshBuilder {
+    group: String,
+    material: Option<String>,
+    material_line: usize,
+    data: MeshData,
+    lookup: HashMap<VertexKey, GLuint>,
+    missing_normals: Vec<bool>,
+}
+
+impl MeshBuilder {
+    fn new(group: &str, material: Option<String>, material_line: usize) -> MeshBuilder {
+        MeshBuilder {
+            group: String::from(group),
+            material,
+            material_line,
+            data: MeshData::new(),
+            lookup: HashMap::new(),
+            missing_normals: Vec::new(),
+        }
+    }
+}
+
+struct ObjParser<'a> {
+    file: &'a str,
+
+    positions: Vec<[f32; 3]>,
+    tex_coords: Vec<[f32; 2]>,
+    normals: Vec<[f32; 3]>,
+
+    material_libraries: Vec<(String, usize)>,
+
+    objects: Vec<(String, Vec<MeshBuilder>)>,
+    group: String,
+    material: Option<String>,
+    material_line: usize,
+    // Index of the mesh for the object, group and material above, None once one of them changes
+    current_mesh: Option<usize>,
+}
+
+impl<'a> ObjParser<'a> {
+    fn new(file: &'a str) -> ObjParser<'a> {
+        ObjParser {
+            file,
+            positions: Vec::new(),
+            tex_coords: Vec::new(),
+            normals: Vec::new(),
+            material_libraries: Vec::new(),
+            objects: Vec::new(),
+            group: String::from("default"),
+            material: None,
+            material_line: 0,
+            current_mesh: None,
+        }
+    }
+
+    fn error(&self, line: usize, message: String) -> Error {
+        Error::Parse { file: String::from(self.file), line, message }
+    }
+
+    fn parse_line(&mut self, line: &str, line_number: usize) -> Result<(), Error> {
+        let line = match line.find('#') {
+            Some(index) => &line[..index],
+            None => line,
+        };
+
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => return Ok(()),
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => {
+                let values = parse_floats(&arguments, 3, self.file, line_number)?;
+                self.positions.push([values[0], values[1], values[2]]);
+            }
+            "vt" => {
+                let values = parse_floats(&arguments, 1, self.file, line_number)?;
+                self.tex_coords.push([values[0], values.get(1).copied().unwrap_or(0.0)]);
+            }
+            "vn" => {
+                let values = parse_floats(&arguments, 3, self.file, line_number)?;
+                self.normals.push([values[0], values[1], values[2]]);
+            }
+            "f" => self.parse_face(&arguments, line_number)?,
+            "o" => {
+                let name = arguments.join(" ");
+                self.objects.push((name, Vec::new()));
+                self.group = String::from("default");
+                self.current_mesh = None;
+            }
+            "g" => {
+                self.group = if arguments.is_empty() { String::from("default") } else { arguments.join(" ") };
+                self.current_mesh = None;
+            }
+            "usemtl" => {
+                if arguments.is_empty() {
+                    return Err(self.error(line_number, String::from("usemtl without a material name")));
+                }
+                self.material = Some(arguments.join(" "));
+                self.material_line = line_number;
+                self.current_mesh = None;
+            }
+            "mtllib" => {
+                for library in arguments {
+                    self.material_libraries.push((String::from(library), line_number));
+                }
+            }
+            // Smoothing groups, lines, points and free-form geometry are not used by the renderer
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Returns the mesh that matches the current object, group and material
+    fn current_mesh(&mut self) -> &mut MeshBuilder {
+        if self.objects.is_empty() {
+            self.objects.push((String::from("default"), Vec::new()));
+        }
+
+        let meshes = &mut self.objects.last_mut().unwrap().1;
+
+        let index = match self.current_mesh {
+            Some(index) => index,
+            None => {
+                // A group can be left and continued later with the same material
+                let position = meshes.iter()
+                    .position(|mesh| mesh.group == self.group && mesh.material == self.material);
+
+                let index = position.unwrap_or_else(|| {
+                    meshes.push(MeshBuilder::new(&self.group, self.material.clone(), self.material_line));
+                    meshes.len() - 1
+                });
+                self.current_mesh = Some(index);
+                index
+            }
+        };
+
+        &mut meshes[index]
+    }
+
+    fn parse_face(&mut self, arguments: &[&str], line_number: usize) -> Result<(), Error> {
+        if arguments.len() < 3 {
+            return Err(self.error(line_number, format!("face needs at least 3 vertices, got {}", arguments.len())));
+        }
+
+        let mut keys: Vec<VertexKey> = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            keys.push(self.parse_face_vertex(argument, line_number)?);
+        }
+
+        let mut face_indices: Vec<GLuint> = Vec::with_capacity(keys.len());
+        for key in keys {
+            let position = self.positions[key.0];
+            let tex_coords = key.1.map(|index| self.tex_coords[index]).unwrap_or([0.0, 0.0]);
+            let normal = key.2.map(|index| self.normals[index]);
+
+            let mesh = self.current_mesh();
+            let index = match mesh.lookup.get(&key) {
+                Some(&index) => index,
+                None => {
+                    let index = mesh.data.push_vertex(position, normal.unwrap_or([0.0, 0.0, 0.0]), tex_coords);
+                    mesh.missing_normals.push(normal.is_none());
+                    mesh.lookup.insert(key, index);
+                    index
+                }
+            };
+
+            face_indices.push(index);
+        }
+
+        // Triangulate the polygon as a fan around the first vertex
+        let mesh = self.current_mesh();
+        for i in 1..face_indices.len() - 1 {
+            mesh.data.indices.push(face_indices[0]);
+            mesh.data.indices.push(face_indices[i]);
+            mesh.data.indices.push(face_indices[i + 1]);
+        }
+
+        Ok(())
+    }
+
+    /// Parses one of "v", "v/vt", "v//vn" or "v/vt/vn"
+    fn parse_face_vertex(&self, argument: &str, line_number: usize) -> Result<VertexKey, Error> {
+        let mut parts = argument.split('/');
+
+        let position = self.resolve_index(parts.next(), self.positions.len(), "position", line_number)?
+            .ok_or_else(|| self.error(line_number, format!("face vertex '{}' has no position", argument)))?;
+        let tex_coords = self.resolve_index(parts.next(), self.tex_coords.len(), "texture coordinate", line_number)?;
+        let normal = self.resolve_index(parts.next(), self.normals.len(), "normal", line_number)?;
+
+        Ok((position, tex_coords, normal))
+    }
+
+    /// Converts a 1 based (or negative, relative to the end) index into a 0 based one
+    fn resolve_index(&self, part: Option<&str>, len: usize, kind: &str, line_number: usize) -> Result<Option<usize>, Error> {
+        let part = match part {
+            Some(part) if !part.is_empty() => part,
+            _ => return Ok(None),
+        };
+
+        let index: i64 = part.parse()
+            .map_err(|_| self.error(line_number, format!("invalid {} index '{}'", kind, part)))?;
+
+        let resolved = if index < 0 { len as i64 + index } else { index - 1 };
+
+        if resolved < 0 || resolved >= len as i64 {
+            return Err(self.error(line_number, format!("{} index {} out of range", kind, index)));
+        }
+
+        Ok(Some(resolved as usize))
+    }
+
+    fn finish(self, materials: &[Material]) -> Result<ObjModel, Error> {
+        let mut objects = Vec::with_capacity(self.objects.len());
+
+        for (name, builders) in self.objects {
+            let mut meshes = Vec::with_capacity(builders.len());
+
+            for mut builder in builders {
+                if builder.data.indices.is_empty() {
+                    continue;
+                }
+
+                builder.data.generate_normals(&builder.missing_normals);
+
+                let material = match &builder.material {
+                    Some(material_name) => Some(
+                        materials.iter()
+                            .position(|material| &material.name == material_name)
+                            .ok_or_else(|| Error::Parse {
+                                file: String::from(self.file),
+                                line: builder.material_line,
+                                message: format!("material '{}' is not defined", material_name),
+                            })?
+                    ),
+                    None => None,
+                };
+
+                meshes.push(ObjMesh {
+                    group: builder.group,
+                    material,
+                    data: builder.data,
+                });
+            }
+
+            objects.push(ObjObject { name, meshes });
+        }
+
+        Ok(ObjModel {
+            objects,
+            materials: materials.to_vec(),
+        })
+    }
+}
+
+fn parse_floats(arguments: &[&str], min: usize, file: &str, line: usize) -> Result<Vec<f32>, Error> {
+    if arguments.len() < min {
+        return Err(Error::Parse {
+            file: String::from(file),
+            line,
+            message: format!("expected at least {} values, got {}", min, arguments.len()),
+        });
+    }
+
+    arguments.iter()
+        .map(|argument| argument.parse::<f32>().map_err(|_| Error::Parse {
+            file: String::from(file),
+            line,
+            message: format!("invalid number '{}'", argument),
+        }))
+        .collect()
+}
+
+fn parse_color(arguments: &[&str], file: &str, line: usize) -> Result<[f32; 3], Error> {
+    let values = parse_floats(arguments, 1, file, line)?;
+
+    // A single value means a grey color
+    if values.len() < 3 {
+        return Ok([values[0], values[0], values[0]]);
+    }
+
+    Ok([values[0], values[1], values[2]])
+}
+
+/// Extracts the file name of a texture map, skipping its options (e.g. "-bm 0.5 bump.png")
+/// The name is resolved relative to `model`, the .obj file
+fn parse_texture_name(arguments: &[&str], file: &str, model: &str, line: usize) -> Result<String, Error> {
+    let mut index = 0;
+
+    while index < arguments.len() && arguments[index].starts_with('-') {
+        let option = arguments[index];
+        index += 1;
+
+        match option {
+            // Between one and three numbers, the file name is always kept
+            "-o" | "-s" | "-t" => {
+                let mut count = 0;
+                while count < 3 && index + 1 < arguments.len() && arguments[index].parse::<f32>().is_ok() {
+                    index += 1;
+                    count += 1;
+                }
+            }
+            "-mm" => index += 2,
+            _ => index += 1,
+        }
+    }
+
+    if index >= arguments.len() {
+        return Err(Error::Parse {
+            file: String::from(file),
+            line,
+            message: String::from("texture map without a file name"),
+        });
+    }
+
+    Ok(relative_resource_name(model, &arguments[index..].join(" ")))
+}
+
+/// `file` is the name of the .mtl resource, `model` the one of the .obj file using it
+fn parse_mtl(source: &str, file: &str, model: &str) -> Result<Vec<Material>, Error> {
+    let mut materials: Vec<Material> = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+
+        let line = match line.find('#') {
+            Some(index) => &line[..index],
+            None => line,
+        };
+
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            materials.push(Material::new(&arguments.join(" ")));
+            continue;
+        }
+
+        let material = materials.last_mut().ok_or_else(|| Error::Parse {
+            file: String::from(file),
+            line: line_number,
+            message: format!("'{}' before any newmtl", keyword),
+        })?;
+
+        match keyword {
+            "Ka" => material.ambient = parse_color(&arguments, file, line_number)?,
+            "Kd" => material.diffuse = parse_color(&arguments, file, line_number)?,
+            "Ks" => material.specular = parse_color(&arguments, file, line_number)?,
+            "Ns" => material.shininess = parse_floats(&arguments, 1, file, line_number)?[0],
+            "d" => material.dissolve = parse_floats(&arguments, 1, file, line_number)?[0],
+            "Tr" => material.dissolve = 1.0 - parse_floats(&arguments, 1, file, line_number)?[0],
+            "map_Kd" => material.diffuse_texture = Some(parse_texture_name(&arguments, file, model, line_number)?),
+            "map_Ks" => material.specular_texture = Some(parse_texture_name(&arguments, file, model, line_number)?),
+            "map_Bump" | "map_bump" | "bump" | "norm" => {
+                material.normal_texture = Some(parse_texture_name(&arguments, file, model, line_number)?)
+            }
+            // Everything else isn't supported by the renderer yet
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io;
+
+    use super::{Error, ObjModel};
+    use crate::graphics::resources;
+
+    fn parse(source: &str, libraries: &[(&str, &str)]) -> Result<ObjModel, Error> {
+        let libraries: HashMap<&str, &str> = libraries.iter().copied().collect();
+
+        ObjModel::parse(source, "models/model.obj", |name| {
+            libraries.get(name)
+                .map(|source| String::from(*source))
+                .ok_or_else(|| resources::Error::Io(io::Error::new(io::ErrorKind::NotFound, name)))
+        })
+    }
+
+    fn error_line(result: Result<ObjModel, Error>) -> (String, usize) {
+        match result {
+            Err(Error::Parse { file, line, .. }) => (file, line),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    const QUAD: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+    #[test]
+    fn polygons_are_triangulated_as_fans() {
+        let model = parse(&format!("{}v 0.5 1.5 0\nf 1 2 3 5 4\n", QUAD), &[]).unwrap();
+        let data = &model.objects[0].meshes[0].data;
+
+        assert_eq!(data.indices, vec![0, 1, 2, 0, 2, 3, 0, 3, 4]);
+        assert_eq!(data.position(3), [0.5, 1.5, 0.0]);
+    }
+
+    #[test]
+    fn negative_indices_are_relative_to_the_end() {
+        let model = parse(&format!("{}vt 0 0\nvt 1 1\nf -4/-2 -3/-1 -2/-1\n", QUAD), &[]).unwrap();
+        let data = &model.objects[0].meshes[0].data;
+
+        assert_eq!(data.vertex_count(), 3);
+        assert_eq!(data.position(2), [1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn identical_vertices_are_shared() {
+        let model = parse(&format!("{}vt 0 0\nvt 1 1\nf 1/1 2/1 3/1\nf 1/1 3/1 4/1\nf 1/2 2/2 3/2\n", QUAD), &[]).unwrap();
+        let data = &model.objects[0].meshes[0].data;
+
+        // The last face uses other texture coordinates, its vertices are new
+        assert_eq!(data.vertex_count(), 7);
+        assert_eq!(data.indices[..6], [0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn meshes_by_object_group_and_material() {
+        let source = format!(
+            "mtllib model.mtl\n{}o first\ng a\nusemtl red\nf 1 2 3\ng b\nf 1 3 4\ng a\nf 2 3 4\no second\nusemtl blue\nf 1 2 3\n",
+            QUAD
+        );
+        let model = parse(&source, &[("models/model.mtl", "newmtl red\nnewmtl blue\n")]).unwrap();
+
+        assert_eq!(model.objects.len(), 2);
+        let first = &model.objects[0];
+        assert_eq!(first.meshes.iter().map(|mesh| mesh.group.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(first.meshes[0].data.triangle_count(), 2);
+        assert_eq!(first.meshes[0].material, Some(0));
+        assert_eq!(model.objects[1].meshes[0].material, Some(1));
+    }
+
+    #[test]
+    fn material_options_and_textures() {
+        let mtl = "\
+newmtl brick
+Kd 0.5 0.25 1
+Ks 0.2
+Ns 32
+d 0.5
+map_Kd -s 2 2 -clamp on textures/brick diffuse.png
+map_Bump -bm 0.5 brick_normal.png
+newmtl glass
+Tr 0.75
+";
+        let model = parse("mtllib ../materials/brick.mtl\n", &[("models/../materials/brick.mtl", mtl)]).unwrap();
+        let brick = &model.materials[0];
+
+        assert_eq!(brick.diffuse, [0.5, 0.25, 1.0]);
+        assert_eq!(brick.specular, [0.2, 0.2, 0.2]);
+        assert_eq!(brick.shininess, 32.0);
+        assert_eq!(brick.dissolve, 0.5);
+        // Relative to the .obj file, not the .mtl one
+        assert_eq!(brick.diffuse_texture.as_deref(), Some("models/textures/brick diffuse.png"));
+        assert_eq!(brick.normal_texture.as_deref(), Some("models/brick_normal.png"));
+        assert_eq!(model.materials[1].dissolve, 0.25);
+    }
+
+    #[test]
+    fn errors_report_the_file_and_line() {
+        let file = String::from("models/model.obj");
+
+        assert_eq!(error_line(parse("v 0 0 0\n\nv 1 x 0\n", &[])), (file.clone(), 3));
+        assert_eq!(error_line(parse(&format!("{}# comment\nf 1 2 9\n", QUAD), &[])), (file.clone(), 6));
+        assert_eq!(error_line(parse(&format!("{}f 1 2\n", QUAD), &[])), (file.clone(), 5));
+        assert_eq!(error_line(parse(&format!("{}\nusemtl missing\nf 1 2 3\n", QUAD), &[])), (file.clone(), 6));
+        assert_eq!(error_line(parse("\nmtllib missing.mtl\n", &[])), (file, 2));
+        assert_eq!(
+            error_line(parse("mtllib model.mtl\n", &[("models/model.mtl", "Kd 1 1 1\n")])),
+            (String::from("models/model.mtl"), 1)
+        );
+    }
+}
@@ -0,0 +1,399 @@
+use std::collections::VecDeque;
+use std::ffi::CString;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Instant;
+
+use gl::types::GLsizei;
+use gl::StateCounters;
+
+use crate::graphics::program::Program;
+use crate::graphics::query::GpuTimer;
+use crate::graphics::render_state::RenderState;
+use crate::graphics::shader::Shader;
+use crate::graphics::vao::VAO;
+use crate::graphics::vbo::VBO;
+
+// Counted by every draw call of the frame, read by `FrameProfiler::end_frame`
+static DRAW_CALLS: AtomicU32 = AtomicU32::new(0);
+static TRIANGLES: AtomicU64 = AtomicU64::new(0);
+
+/// Counts a draw call in the current frame's statistics
+pub fn record_draw_call(triangles: u64) {
+    DRAW_CALLS.fetch_add(1, Ordering::Relaxed);
+    TRIANGLES.fetch_add(triangles, Ordering::Relaxed);
+}
+
+/// Measurements of a single frame, times in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameSample {
+    pub frame: u64,
+    /// Time since the start of the previous frame
+    pub frame_ms: f32,
+    /// Time spent on the CPU between `begin_frame` and `end_frame`
+    pub cpu_ms: f32,
+    /// Filled a few frames later, when the GPU is done with the frame
+    pub gpu_ms: Option<f32>,
+    pub draw_calls: u32,
+    pub triangles: u64,
+    /// State changes sent to the driver and skipped by the `gl::Gl` state cache
+    pub state_calls_issued: u64,
+    pub state_calls_skipped: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimingStats {
+    pub average: f32,
+    pub p50: f32,
+    pub p95: f32,
+    pub p99: f32,
+    pub max: f32,
+}
+
+impl TimingStats {
+    fn from_times(mut times: Vec<f32>) -> TimingStats {
+        if times.is_empty() {
+            return TimingStats::default();
+        }
+
+        times.sort_by(f32::total_cmp);
+
+        let percentile = |p: f32| times[((times.len() - 1) as f32 * p).round() as usize];
+
+        TimingStats {
+            average: times.iter().sum::<f32>() / times.len() as f32,
+            p50: percentile(0.5),
+            p95: percentile(0.95),
+            p99: percentile(0.99),
+            max: times[times.len() - 1],
+        }
+    }
+}
+
+/// Samples of the last `capacity` frames, oldest first
+#[derive(Debug, Clone)]
+struct FrameHistory {
+    samples: VecDeque<FrameSample>,
+    capacity: usize,
+}
+
+impl FrameHistory {
+    fn new(capacity: usize) -> FrameHistory {
+        FrameHistory {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Drops the oldest sample once the history is full
+    fn push(&mut self, sample: FrameSample) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        while self.samples.len() >= self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Frames already dropped from the history are ignored
+    fn set_gpu_time(&mut self, frame: u64, gpu_ms: f32) {
+        if let Some(sample) = self.samples.iter_mut().find(|sample| sample.frame == frame) {
+            sample.gpu_ms = Some(gpu_ms);
+        }
+    }
+}
+
+/// Keeps the samples of the last frames
+pub struct FrameProfiler {
+    gl: gl::Gl,
+    history: FrameHistory,
+    gpu_timer: GpuTimer,
+    frame: u64,
+    frame_start: Option<Instant>,
+    previous_frame_start: Option<Instant>,
+    state_counters_start: StateCounters,
+}
+
+impl FrameProfiler {
+    /// `capacity` is the number of frames the averages and percentiles are computed over
+    pub fn new(gl: &gl::Gl, capacity: usize) -> FrameProfiler {
+        FrameProfiler {
+            gl: gl.clone(),
+            history: FrameHistory::new(capacity),
+            gpu_timer: GpuTimer::new(gl),
+            frame: 0,
+            frame_start: None,
+            previous_frame_start: None,
+            state_counters_start: gl.state_counters(),
+        }
+    }
+
+    pub fn begin_frame(&mut self) {
+        let now = Instant::now();
+        self.previous_frame_start = self.frame_start.replace(now);
+
+        DRAW_CALLS.store(0, Ordering::Relaxed);
+        TRIANGLES.store(0, Ordering::Relaxed);
+        self.state_counters_start = self.gl.state_counters();
+
+        self.gpu_timer.begin(self.frame);
+    }
+
+    /// Call before swapping the buffers so waiting for vsync is not counted as CPU time
+    pub fn end_frame(&mut self) {
+        self.gpu_timer.end();
+
+        let frame_start = match self.frame_start {
+            Some(frame_start) => frame_start,
+            None => return,
+        };
+
+        let cpu_ms = frame_start.elapsed().as_secs_f32() * 1000.0;
+        let frame_ms = match self.previous_frame_start {
+            Some(previous) => frame_start.duration_since(previous).as_secs_f32() * 1000.0,
+            None => cpu_ms,
+        };
+
+        // Reset during the frame, count from the reset
+        let state_counters = self.gl.state_counters();
+        let state_calls = if state_counters.is_before(&self.state_counters_start) {
+            state_counters
+        } else {
+            state_counters - self.state_counters_start
+        };
+
+        self.history.push(FrameSample {
+            frame: self.frame,
+            frame_ms,
+            cpu_ms,
+            gpu_ms: None,
+            draw_calls: DRAW_CALLS.load(Ordering::Relaxed),
+            triangles: TRIANGLES.load(Ordering::Relaxed),
+            state_calls_issued: state_calls.issued,
+            state_calls_skipped: state_calls.skipped,
+        });
+
+        for (frame, gpu_ms) in self.gpu_timer.poll() {
+            self.history.set_gpu_time(frame, gpu_ms);
+        }
+
+        self.frame += 1;
+    }
+
+    /// Oldest first
+    pub fn samples(&self) -> impl Iterator<Item = &FrameSample> {
+        self.history.samples.iter()
+    }
+
+    pub fn latest(&self) -> Option<&FrameSample> {
+        self.history.samples.back()
+    }
+
+    pub fn frame_stats(&self) -> TimingStats {
+        TimingStats::from_times(self.history.samples.iter().map(|sample| sample.frame_ms).collect())
+    }
+
+    pub fn cpu_stats(&self) -> TimingStats {
+        TimingStats::from_times(self.history.samples.iter().map(|sample| sample.cpu_ms).collect())
+    }
+
+    /// Only over the frames whose GPU time came back
+    pub fn gpu_stats(&self) -> TimingStats {
+        TimingStats::from_times(self.history.samples.iter().filter_map(|sample| sample.gpu_ms).collect())
+    }
+
+    /// One line per frame of the history, GPU times not available yet are left empty
+    pub fn export_csv(&self, path: &Path) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+
+        writeln!(file, "frame,frame_ms,cpu_ms,gpu_ms,draw_calls,triangles,state_calls_issued,state_calls_skipped")?;
+        for sample in &self.history.samples {
+            let gpu_ms = sample.gpu_ms.map(|gpu_ms| gpu_ms.to_string()).unwrap_or_default();
+            writeln!(
+                file, "{},{},{},{},{},{},{},{}",
+                sample.frame, sample.frame_ms, sample.cpu_ms, gpu_ms, sample.draw_calls, sample.triangles,
+                sample.state_calls_issued, sample.state_calls_skipped
+            )?;
+        }
+
+        file.flush()
+    }
+
+    pub fn delete(&self) {
+        self.gpu_timer.delete();
+    }
+}
+
+/// Bar graph of the frame times drawn over the bottom left corner of the screen
+/// Green/orange/red bars are the frame times, the thin blue ones the GPU times,
+/// the white line is the `target_ms` budget
+pub struct ProfilerOverlay {
+    gl: gl::Gl,
+    program: Program,
+    vao: VAO,
+    vbo: VBO,
+    /// Frame time at the top of the graph
+    pub max_ms: f32,
+    pub target_ms: f32,
+}
+
+/// Position(2) color(3)
+const FLOATS_PER_OVERLAY_VERTEX: usize = 5;
+
+impl ProfilerOverlay {
+    pub fn new(gl: &gl::Gl) -> Result<ProfilerOverlay, String> {
+        let vert_shader = Shader::from_vert_source(
+            gl,
+            &CString::new(include_str!("../../assets/shaders/overlay.vert")).unwrap()
+        )?;
+        let frag_shader = Shader::from_frag_source(
+            gl,
+            &CString::new(include_str!("../../assets/shaders/overlay.frag")).unwrap()
+        )?;
+        let program = Program::from_shaders(gl, &[vert_shader, frag_shader])?;
+        program.set_label("profiler overlay");
+
+        let vao = VAO::new(gl);
+        let vbo = VBO::from_slice::<f32>(gl, &[]);
+
+        let stride = (FLOATS_PER_OVERLAY_VERTEX * std::mem::size_of::<f32>()) as GLsizei;
+        vao.bind();
+        vao.link_attrib(&vbo, 0, 2, stride, 0);
+        vao.link_attrib(&vbo, 1, 3, stride, 2 * std::mem::size_of::<f32>());
+        vao.unbind();
+        vao.set_label("profiler overlay");
+        vbo.set_label("profiler overlay vertices");
+
+        Ok(ProfilerOverlay {
+            gl: gl.clone(),
+            program,
+            vao,
+            vbo,
+            max_ms: 50.0,
+            target_ms: 1000.0 / 60.0,
+        })
+    }
+
+    pub fn draw(&self, profiler: &FrameProfiler) {
+        // Graph area in normalized device coordinates
+        let (left, bottom, width, height) = (-0.98, -0.98, 0.8, 0.4);
+
+        let bar_width = width / profiler.history.capacity.max(1) as f32;
+        let to_y = |ms: f32| bottom + height * (ms / self.max_ms).min(1.0);
+
+        let mut vertices: Vec<f32> = Vec::new();
+        let mut quad = |x0: f32, y0: f32, x1: f32, y1: f32, color: [f32; 3]| {
+            for (x, y) in [(x0, y0), (x1, y0), (x1, y1), (x0, y0), (x1, y1), (x0, y1)] {
+                vertices.extend_from_slice(&[x, y, color[0], color[1], color[2]]);
+            }
+        };
+
+        // Background
+        quad(left, bottom, left + width, bottom + height, [0.1, 0.1, 0.1]);
+
+        for (index, sample) in profiler.samples().enumerate() {
+            let x = left + index as f32 * bar_width;
+
+            let color = if sample.frame_ms <= self.target_ms {
+                [0.2, 0.8, 0.2]
+            } else if sample.frame_ms <= self.target_ms * 2.0 {
+                [0.9, 0.6, 0.1]
+            } else {
+                [0.9, 0.2, 0.2]
+            };
+            quad(x, bottom, x + bar_width, to_y(sample.frame_ms), color);
+
+            if let Some(gpu_ms) = sample.gpu_ms {
+                quad(x + bar_width * 0.25, bottom, x + bar_width * 0.75, to_y(gpu_ms), [0.3, 0.5, 1.0]);
+            }
+        }
+
+        let target_y = to_y(self.target_ms);
+        quad(left, target_y - 0.002, left + width, target_y + 0.002, [1.0, 1.0, 1.0]);
+
+        self.vbo.update(&vertices);
+
+        RenderState::overlay().apply(&self.gl);
+
+        self.program.set_used();
+        self.vao.bind();
+        unsafe { self.gl.DrawArrays(gl::TRIANGLES, 0, (vertices.len() / FLOATS_PER_OVERLAY_VERTEX) as GLsizei); }
+    }
+
+    pub fn delete(&self) {
+        self.vao.delete();
+        self.vbo.delete();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FrameHistory, FrameSample, TimingStats};
+
+    fn sample(frame: u64, frame_ms: f32) -> FrameSample {
+        FrameSample {
+            frame,
+            frame_ms,
+            cpu_ms: 0.0,
+            gpu_ms: None,
+            draw_calls: 0,
+            triangles: 0,
+            state_calls_issued: 0,
+            state_calls_skipped: 0,
+        }
+    }
+
+    #[test]
+    fn percentiles_of_sorted_times() {
+        // 1 to 100 ms, shuffled
+        let times: Vec<f32> = (0..100).map(|i| ((i * 37) % 100 + 1) as f32).collect();
+        let stats = TimingStats::from_times(times);
+
+        assert_eq!(stats.average, 50.5);
+        assert_eq!(stats.p50, 51.0);
+        assert_eq!(stats.p95, 95.0);
+        assert_eq!(stats.p99, 99.0);
+        assert_eq!(stats.max, 100.0);
+    }
+
+    #[test]
+    fn percentiles_of_few_times() {
+        assert_eq!(TimingStats::from_times(Vec::new()), TimingStats::default());
+
+        let stats = TimingStats::from_times(vec![4.0]);
+        assert_eq!((stats.p50, stats.p99, stats.max), (4.0, 4.0, 4.0));
+
+        // NaN sorts last instead of panicking
+        let stats = TimingStats::from_times(vec![f32::NAN, 2.0, 1.0]);
+        assert_eq!(stats.p50, 2.0);
+        assert!(stats.max.is_nan());
+    }
+
+    #[test]
+    fn history_keeps_the_last_frames() {
+        let mut history = FrameHistory::new(3);
+        for frame in 0..5 {
+            history.push(sample(frame, frame as f32));
+        }
+
+        let frames: Vec<u64> = history.samples.iter().map(|sample| sample.frame).collect();
+        assert_eq!(frames, vec![2, 3, 4]);
+
+        history.set_gpu_time(3, 1.5);
+        history.set_gpu_time(0, 9.0);
+        let gpu_times: Vec<Option<f32>> = history.samples.iter().map(|sample| sample.gpu_ms).collect();
+        assert_eq!(gpu_times, vec![None, Some(1.5), None]);
+    }
+
+    #[test]
+    fn empty_history_stays_empty() {
+        let mut history = FrameHistory::new(0);
+        history.push(sample(0, 1.0));
+
+        assert!(history.samples.is_empty());
+    }
+}
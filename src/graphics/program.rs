@@ -1,12 +1,14 @@
 use std::ffi::CString;
 use gl;
-use gl::types::{GLint, GLuint};
+use gl::types::{GLint, GLsizei, GLuint};
+use nalgebra::Matrix4;
 
 use crate::graphics::{
     create_whitespace_cstring_with_len,
 };
 
-use crate::Shader;
+use crate::graphics::shader::Shader;
+use crate::graphics::debug;
 
 pub struct Program {
     gl : gl::Gl,
@@ -68,9 +70,17 @@ impl Program {
     }
 
     pub fn set_used(&self) {
-        unsafe {
-            self.gl.UseProgram(self.id);
-        }
+        self.gl.use_program(self.id);
+    }
+
+    /// Hands the program over to another context of the share group, the uniform values set so far come along
+    pub fn adopt(&mut self, gl : &gl::Gl){
+        debug_assert!(self.gl.shares_objects_with(gl), "program {} is not shared with GL context {}", self.id, gl.context_id());
+        self.gl = gl.clone();
+    }
+
+    pub fn set_label(&self, label : &str){
+        debug::object_label(&self.gl, gl::PROGRAM, self.id, label);
     }
 
     pub fn id(&self) -> GLuint {
@@ -78,13 +88,12 @@ impl Program {
     }
 
     pub fn activate(&self){
-        unsafe {
-            self.gl.UseProgram(self.id);
-        }
+        self.gl.use_program(self.id);
     }
 
     pub fn get_uniform_id(&self, name : &str) -> GLint{
-        let mut uni_id : GLint;
+        self.gl.debug_assert_current();
+        let uni_id : GLint;
         unsafe {
             let name = CString::new(String::from(name)).expect("CString::new failed");
             uni_id = self.gl.GetUniformLocation(self.id, name.as_ptr());
@@ -92,12 +101,42 @@ impl Program {
         uni_id
     }
 
+    pub fn set_float_array(&self, name : &str, values : &[f32]){
+        unsafe {
+            self.gl.Uniform1fv(
+                self.get_uniform_id(name),
+                values.len() as GLsizei,
+                values.as_ptr()
+            );
+        }
+    }
+
+    /// Connects the uniform block `name` to the buffers bound at `binding`
+    pub fn bind_uniform_block(&self, name : &str, binding : GLuint){
+        let name = CString::new(String::from(name)).expect("CString::new failed");
+        unsafe {
+            let index = self.gl.GetUniformBlockIndex(self.id, name.as_ptr());
+            if index != gl::INVALID_INDEX {
+                self.gl.UniformBlockBinding(self.id, index, binding);
+            }
+        }
+    }
+
+    pub fn set_matrix4(&self, name : &str, matrix : &Matrix4<f32>){
+        unsafe {
+            self.gl.UniformMatrix4fv(
+                self.get_uniform_id(name),
+                1,
+                gl::FALSE,
+                matrix.as_ptr()
+            );
+        }
+    }
+
 }
 
 impl Drop for Program {
     fn drop(&mut self) {
-        unsafe {
-            self.gl.DeleteProgram(self.id);
-        }
+        self.gl.delete_program(self.id);
     }
 }
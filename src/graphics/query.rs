@@ -0,0 +1,239 @@
+use gl::types::{GLenum, GLint, GLuint, GLuint64};
+
+/// What a query measures between `begin` and `end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryKind {
+    /// GPU time in nanoseconds
+    TimeElapsed,
+    /// Number of samples passing the depth and stencil tests
+    SamplesPassed,
+    /// 1 if any sample passed, can be faster than counting them
+    AnySamplesPassed,
+    /// Like `AnySamplesPassed` but may report false positives, faster on some GPUs (GL 4.3)
+    AnySamplesPassedConservative,
+    /// Primitives produced by the vertex/geometry stages, before clipping
+    PrimitivesGenerated,
+}
+
+impl QueryKind {
+    pub fn target(&self) -> GLenum {
+        match self {
+            QueryKind::TimeElapsed => gl::TIME_ELAPSED,
+            QueryKind::SamplesPassed => gl::SAMPLES_PASSED,
+            QueryKind::AnySamplesPassed => gl::ANY_SAMPLES_PASSED,
+            QueryKind::AnySamplesPassedConservative => gl::ANY_SAMPLES_PASSED_CONSERVATIVE,
+            QueryKind::PrimitivesGenerated => gl::PRIMITIVES_GENERATED,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryState {
+    /// Never started, or its result has been read
+    Idle,
+    Running,
+    /// Ended, the GPU may still be working on it
+    Pending,
+}
+
+/// A GL query object
+/// Only one query of each kind can run at a time
+pub struct Query {
+    gl : gl::Gl,
+    pub id : GLuint,
+    kind : QueryKind,
+    state : QueryState,
+}
+
+impl Query {
+    pub fn new(gl : &gl::Gl, kind : QueryKind) -> Query {
+        let mut id : GLuint = 0;
+        unsafe { gl.GenQueries(1, &mut id); }
+
+        Query {
+            gl : gl.clone(),
+            id,
+            kind,
+            state : QueryState::Idle,
+        }
+    }
+
+    pub fn kind(&self) -> QueryKind {
+        self.kind
+    }
+
+    pub fn state(&self) -> QueryState {
+        self.state
+    }
+
+    /// Starts measuring, a pending result is dropped
+    pub fn begin(&mut self) {
+        debug_assert!(self.state != QueryState::Running, "query {} is already running", self.id);
+        self.gl.debug_assert_current();
+
+        unsafe { self.gl.BeginQuery(self.kind.target(), self.id); }
+        self.state = QueryState::Running;
+    }
+
+    pub fn end(&mut self) {
+        debug_assert!(self.state == QueryState::Running, "query {} is not running", self.id);
+
+        unsafe { self.gl.EndQuery(self.kind.target()); }
+        self.state = QueryState::Pending;
+    }
+
+    /// True once the result can be read without waiting for the GPU
+    pub fn is_available(&self) -> bool {
+        if self.state != QueryState::Pending {
+            return false;
+        }
+
+        let mut available : GLint = 0;
+        unsafe { self.gl.GetQueryObjectiv(self.id, gl::QUERY_RESULT_AVAILABLE, &mut available); }
+
+        available != 0
+    }
+
+    /// The result if the GPU is done, never blocks
+    /// Nanoseconds for timers, a count of samples or primitives, or 0/1 for the any-samples queries
+    pub fn try_result(&mut self) -> Option<u64> {
+        if !self.is_available() {
+            return None;
+        }
+
+        Some(self.read_result())
+    }
+
+    /// Waits for the GPU to finish the query, `None` if it was never ended
+    pub fn result_blocking(&mut self) -> Option<u64> {
+        if self.state != QueryState::Pending {
+            return None;
+        }
+
+        Some(self.read_result())
+    }
+
+    fn read_result(&mut self) -> u64 {
+        let mut result : GLuint64 = 0;
+        unsafe { self.gl.GetQueryObjectui64v(self.id, gl::QUERY_RESULT, &mut result); }
+
+        self.state = QueryState::Idle;
+        result
+    }
+
+    /// Draws issued until `end_conditional_render` are skipped by the GPU
+    /// if this occlusion query saw no sample pass, without the CPU reading the result
+    /// `wait` makes the GPU wait for the result instead of drawing when it is not ready
+    pub fn begin_conditional_render(&self, wait : bool) {
+        let mode = if wait { gl::QUERY_WAIT } else { gl::QUERY_NO_WAIT };
+        unsafe { self.gl.BeginConditionalRender(self.id, mode); }
+    }
+
+    pub fn end_conditional_render(&self) {
+        unsafe { self.gl.EndConditionalRender(); }
+    }
+
+    pub fn delete(&self) {
+        unsafe { self.gl.DeleteQueries(1, &self.id); }
+    }
+}
+
+/// Queries reused frame after frame, each result tagged (e.g. with the frame number)
+/// Results are read when available, so there is no stall as long as the GPU
+/// is less than `capacity` measurements behind
+pub struct QueryRing {
+    queries : Vec<Query>,
+    tags : Vec<Option<u64>>,
+    next : usize,
+    running : bool,
+}
+
+impl QueryRing {
+    pub fn new(gl : &gl::Gl, kind : QueryKind, capacity : usize) -> QueryRing {
+        QueryRing {
+            queries : (0..capacity).map(|_| Query::new(gl, kind)).collect(),
+            tags : vec![None; capacity],
+            next : 0,
+            running : false,
+        }
+    }
+
+    /// Starts a measurement, skipped (returns false) if every query is still waiting for the GPU
+    pub fn begin(&mut self, tag : u64) -> bool {
+        if self.tags[self.next].is_some() {
+            return false;
+        }
+
+        self.queries[self.next].begin();
+        self.tags[self.next] = Some(tag);
+        self.running = true;
+
+        true
+    }
+
+    pub fn end(&mut self) {
+        if !self.running {
+            return;
+        }
+
+        self.queries[self.next].end();
+        self.running = false;
+        self.next = (self.next + 1) % self.queries.len();
+    }
+
+    /// Tags and results of the measurements the GPU is done with
+    pub fn poll(&mut self) -> Vec<(u64, u64)> {
+        let mut results = Vec::new();
+
+        for (query, tag) in self.queries.iter_mut().zip(self.tags.iter_mut()) {
+            if let Some(result) = tag.and_then(|_| query.try_result()) {
+                results.push((tag.take().unwrap(), result));
+            }
+        }
+
+        results
+    }
+
+    pub fn delete(&self) {
+        for query in &self.queries {
+            query.delete();
+        }
+    }
+}
+
+/// Number of frames a timer result can take to come back before its query is reused
+const TIMER_LATENCY: usize = 4;
+
+/// Measures the GPU time of whole frames
+pub struct GpuTimer {
+    ring : QueryRing,
+}
+
+impl GpuTimer {
+    pub fn new(gl : &gl::Gl) -> GpuTimer {
+        GpuTimer {
+            ring : QueryRing::new(gl, QueryKind::TimeElapsed, TIMER_LATENCY),
+        }
+    }
+
+    /// Starts timing `frame`, skipped if the GPU is so far behind that every query is still pending
+    pub fn begin(&mut self, frame : u64) {
+        self.ring.begin(frame);
+    }
+
+    pub fn end(&mut self) {
+        self.ring.end();
+    }
+
+    /// Frames whose GPU time (in milliseconds) is available, without blocking
+    pub fn poll(&mut self) -> Vec<(u64, f32)> {
+        self.ring.poll()
+            .into_iter()
+            .map(|(frame, nanoseconds)| (frame, nanoseconds as f32 / 1.0e6))
+            .collect()
+    }
+
+    pub fn delete(&self) {
+        self.ring.delete();
+    }
+}
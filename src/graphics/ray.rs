@@ -0,0 +1,219 @@
+use nalgebra::{Matrix4, Point3, Vector3};
+
+use crate::graphics::bounds::{Aabb, BoundingSphere};
+
+/// Half-line starting at `origin`
+/// Distances are given in multiples of `direction`, so they are in world units when it is normalized
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    pub fn new(origin: Point3<f32>, direction: Vector3<f32>) -> Ray {
+        Ray { origin, direction }
+    }
+
+    pub fn at(&self, distance: f32) -> Point3<f32> {
+        self.origin + self.direction * distance
+    }
+
+    /// Ray in the space `matrix` transforms to, distances along it stay the same
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Ray {
+        Ray::new(matrix.transform_point(&self.origin), matrix.transform_vector(&self.direction))
+    }
+
+    /// Möller–Trumbore intersection, both faces of the triangle are hit
+    pub fn intersect_triangle(&self, a: &Point3<f32>, b: &Point3<f32>, c: &Point3<f32>) -> Option<f32> {
+        let edge_ab = b - a;
+        let edge_ac = c - a;
+
+        let p = self.direction.cross(&edge_ac);
+        let determinant = edge_ab.dot(&p);
+
+        // The ray is parallel to the triangle
+        if determinant.abs() < 1.0e-8 {
+            return None;
+        }
+
+        let inverse_determinant = 1.0 / determinant;
+        let to_origin = self.origin - a;
+
+        let u = to_origin.dot(&p) * inverse_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = to_origin.cross(&edge_ab);
+        let v = self.direction.dot(&q) * inverse_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = edge_ac.dot(&q) * inverse_determinant;
+        if distance < 0.0 {
+            return None;
+        }
+
+        Some(distance)
+    }
+
+    /// Slab test, returns 0 when the origin is inside the box
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let mut near = 0.0f32;
+        let mut far = f32::INFINITY;
+
+        for axis in 0..3 {
+            let origin = self.origin[axis];
+            let direction = self.direction[axis];
+
+            if direction.abs() < 1.0e-8 {
+                // Parallel to the slab, it has to start between its planes
+                if origin < aabb.min[axis] || origin > aabb.max[axis] {
+                    return None;
+                }
+                continue;
+            }
+
+            let mut entry = (aabb.min[axis] - origin) / direction;
+            let mut exit = (aabb.max[axis] - origin) / direction;
+            if entry > exit {
+                std::mem::swap(&mut entry, &mut exit);
+            }
+
+            near = near.max(entry);
+            far = far.min(exit);
+
+            if near > far {
+                return None;
+            }
+        }
+
+        Some(near)
+    }
+
+    /// Returns 0 when the origin is inside the sphere
+    pub fn intersect_sphere(&self, sphere: &BoundingSphere) -> Option<f32> {
+        let to_center = sphere.center - self.origin;
+
+        let a = self.direction.norm_squared();
+        let b = self.direction.dot(&to_center);
+        let c = to_center.norm_squared() - sphere.radius * sphere.radius;
+
+        if c <= 0.0 {
+            return Some(0.0);
+        }
+
+        let discriminant = b * b - a * c;
+        if discriminant < 0.0 || a == 0.0 {
+            return None;
+        }
+
+        let distance = (b - discriminant.sqrt()) / a;
+        if distance < 0.0 {
+            return None;
+        }
+
+        Some(distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Matrix4, Point3, Vector3};
+
+    use super::Ray;
+    use crate::graphics::bounds::{Aabb, BoundingSphere};
+
+    fn unit_box() -> Aabb {
+        Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0))
+    }
+
+    fn assert_close(actual: Option<f32>, expected: f32) {
+        let actual = actual.expect("expected a hit");
+        assert!((actual - expected).abs() < 1.0e-5, "hit at {}, expected {}", actual, expected);
+    }
+
+    #[test]
+    fn aabb_hit() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        assert_close(ray.intersect_aabb(&unit_box()), 4.0);
+    }
+
+    #[test]
+    fn aabb_miss() {
+        // Passing beside the box, and pointing away from it
+        let beside = Ray::new(Point3::new(2.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        let away = Ray::new(Point3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert_eq!(beside.intersect_aabb(&unit_box()), None);
+        assert_eq!(away.intersect_aabb(&unit_box()), None);
+    }
+
+    #[test]
+    fn aabb_from_inside() {
+        let ray = Ray::new(Point3::new(0.5, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert_close(ray.intersect_aabb(&unit_box()), 0.0);
+    }
+
+    #[test]
+    fn aabb_diagonal() {
+        let ray = Ray::new(Point3::new(3.0, 3.0, 0.0), Vector3::new(-1.0, -1.0, 0.0).normalize());
+        assert_close(ray.intersect_aabb(&unit_box()), 2.0 * 2.0f32.sqrt());
+    }
+
+    #[test]
+    fn triangle_hit() {
+        let ray = Ray::new(Point3::new(0.25, 0.25, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit = ray.intersect_triangle(
+            &Point3::new(0.0, 0.0, 0.0),
+            &Point3::new(1.0, 0.0, 0.0),
+            &Point3::new(0.0, 1.0, 0.0),
+        );
+
+        assert_close(hit, 1.0);
+    }
+
+    #[test]
+    fn triangle_back_face_hit() {
+        let ray = Ray::new(Point3::new(0.25, 0.25, -2.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = ray.intersect_triangle(
+            &Point3::new(0.0, 0.0, 0.0),
+            &Point3::new(1.0, 0.0, 0.0),
+            &Point3::new(0.0, 1.0, 0.0),
+        );
+
+        assert_close(hit, 2.0);
+    }
+
+    #[test]
+    fn triangle_miss() {
+        let (a, b, c) = (Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0));
+
+        // Outside the edges, behind the origin, parallel to the triangle
+        let outside = Ray::new(Point3::new(0.75, 0.75, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let behind = Ray::new(Point3::new(0.25, 0.25, 1.0), Vector3::new(0.0, 0.0, 1.0));
+        let parallel = Ray::new(Point3::new(0.25, 0.25, 1.0), Vector3::new(1.0, 0.0, 0.0));
+
+        assert_eq!(outside.intersect_triangle(&a, &b, &c), None);
+        assert_eq!(behind.intersect_triangle(&a, &b, &c), None);
+        assert_eq!(parallel.intersect_triangle(&a, &b, &c), None);
+    }
+
+    #[test]
+    fn sphere_hit() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        assert_close(ray.intersect_sphere(&BoundingSphere::new(Point3::origin(), 1.0)), 4.0);
+    }
+
+    #[test]
+    fn transform_keeps_distances() {
+        // Same ray and box, moved together
+        let matrix = Matrix4::new_translation(&Vector3::new(10.0, 0.0, 0.0));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0)).transform(&matrix);
+        let aabb = unit_box().transform(&matrix);
+
+        assert_close(ray.intersect_aabb(&aabb), 4.0);
+    }
+}
@@ -0,0 +1,278 @@
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthState {
+    pub test : bool,
+    pub write : bool,
+    /// gl::LESS, gl::GREATER for a reversed depth...
+    pub func : GLenum,
+}
+
+impl Default for DepthState {
+    fn default() -> DepthState {
+        DepthState {
+            test : true,
+            write : true,
+            func : gl::LESS,
+        }
+    }
+}
+
+/// How the fragments are combined with what is already in the framebuffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Blending disabled
+    Opaque,
+    /// Classic transparency, `color * alpha + destination * (1 - alpha)`
+    Alpha,
+    /// `color * alpha + destination`, for lights, fire, particles
+    Additive,
+    /// Alpha blending for colors already multiplied by their alpha, `color + destination * (1 - alpha)`
+    Premultiplied,
+}
+
+impl BlendMode {
+    /// Source and destination factors of the color, `None` if blending is disabled
+    /// The alpha is always accumulated like premultiplied alpha so the framebuffer's alpha stays meaningful
+    fn factors(&self) -> Option<(GLenum, GLenum)> {
+        match self {
+            BlendMode::Opaque => None,
+            BlendMode::Alpha => Some((gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA)),
+            BlendMode::Additive => Some((gl::SRC_ALPHA, gl::ONE)),
+            BlendMode::Premultiplied => Some((gl::ONE, gl::ONE_MINUS_SRC_ALPHA)),
+        }
+    }
+}
+
+/// Faces that are not drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CullMode {
+    None,
+    Back,
+    Front,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolygonMode {
+    Fill,
+    /// Wireframe
+    Line,
+    Point,
+}
+
+impl PolygonMode {
+    fn to_gl(self) -> GLenum {
+        match self {
+            PolygonMode::Fill => gl::FILL,
+            PolygonMode::Line => gl::LINE,
+            PolygonMode::Point => gl::POINT,
+        }
+    }
+}
+
+/// Stencil test and update, the same for both faces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilState {
+    /// The test compares `reference & read_mask` to `stencil & read_mask`
+    pub func : GLenum,
+    pub reference : GLint,
+    pub read_mask : GLuint,
+    /// Bits of the stencil buffer the operations can change
+    pub write_mask : GLuint,
+    /// Operation when the stencil test fails
+    pub stencil_fail : GLenum,
+    /// Operation when the stencil test passes but the depth test fails
+    pub depth_fail : GLenum,
+    /// Operation when both tests pass
+    pub pass : GLenum,
+}
+
+impl StencilState {
+    /// Writes `reference` wherever something is drawn, e.g. to mark an outline
+    pub fn write(reference : GLint) -> StencilState {
+        StencilState {
+            func : gl::ALWAYS,
+            reference,
+            read_mask : 0xFF,
+            write_mask : 0xFF,
+            stencil_fail : gl::KEEP,
+            depth_fail : gl::KEEP,
+            pass : gl::REPLACE,
+        }
+    }
+
+    /// Draws only where the stencil buffer is `func` `reference`, without changing it
+    pub fn test(func : GLenum, reference : GLint) -> StencilState {
+        StencilState {
+            func,
+            reference,
+            read_mask : 0xFF,
+            write_mask : 0x00,
+            stencil_fail : gl::KEEP,
+            depth_fail : gl::KEEP,
+            pass : gl::KEEP,
+        }
+    }
+}
+
+/// Rectangle in pixels from the bottom left corner of the framebuffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scissor {
+    pub x : GLint,
+    pub y : GLint,
+    pub width : GLsizei,
+    pub height : GLsizei,
+}
+
+/// Fixed function state a pass draws with
+///
+/// Applying it only sends what differs from the current state, every field is set so
+/// a pass doesn't depend on what the previous one left behind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderState {
+    pub depth : DepthState,
+    pub blend : BlendMode,
+    pub cull : CullMode,
+    /// Winding of the front faces, gl::CCW or gl::CW
+    pub front_face : GLenum,
+    /// `None` disables the stencil test
+    pub stencil : Option<StencilState>,
+    pub polygon_mode : PolygonMode,
+    /// `None` disables the scissor test
+    pub scissor : Option<Scissor>,
+}
+
+impl Default for RenderState {
+    fn default() -> RenderState {
+        RenderState::opaque()
+    }
+}
+
+impl RenderState {
+    /// Depth tested and written, no blending, both faces drawn
+    pub fn opaque() -> RenderState {
+        RenderState {
+            depth : DepthState::default(),
+            blend : BlendMode::Opaque,
+            cull : CullMode::None,
+            front_face : gl::CCW,
+            stencil : None,
+            polygon_mode : PolygonMode::Fill,
+            scissor : None,
+        }
+    }
+
+    /// Alpha blended, depth tested but not written so what is behind stays visible
+    /// Draw it after the opaque objects, sorted back to front
+    pub fn transparent() -> RenderState {
+        RenderState {
+            depth : DepthState { write : false, ..DepthState::default() },
+            blend : BlendMode::Alpha,
+            ..RenderState::opaque()
+        }
+    }
+
+    /// Like `transparent` with additive blending, the order doesn't matter
+    pub fn additive() -> RenderState {
+        RenderState {
+            blend : BlendMode::Additive,
+            ..RenderState::transparent()
+        }
+    }
+
+    /// Drawn over everything, e.g. debug graphs and UI
+    pub fn overlay() -> RenderState {
+        RenderState {
+            depth : DepthState { test : false, write : false, ..DepthState::default() },
+            blend : BlendMode::Alpha,
+            ..RenderState::opaque()
+        }
+    }
+
+    pub fn depth_func(mut self, func : GLenum) -> RenderState {
+        self.depth.func = func;
+        self
+    }
+
+    pub fn depth_write(mut self, write : bool) -> RenderState {
+        self.depth.write = write;
+        self
+    }
+
+    pub fn blend(mut self, blend : BlendMode) -> RenderState {
+        self.blend = blend;
+        self
+    }
+
+    pub fn cull(mut self, cull : CullMode) -> RenderState {
+        self.cull = cull;
+        self
+    }
+
+    pub fn stencil(mut self, stencil : Option<StencilState>) -> RenderState {
+        self.stencil = stencil;
+        self
+    }
+
+    pub fn polygon_mode(mut self, polygon_mode : PolygonMode) -> RenderState {
+        self.polygon_mode = polygon_mode;
+        self
+    }
+
+    pub fn scissor(mut self, scissor : Option<Scissor>) -> RenderState {
+        self.scissor = scissor;
+        self
+    }
+
+    /// Sets the GL state, the calls that would change nothing are skipped by the `gl::Gl` state cache
+    /// The depth and stencil masks also limit what `glClear` clears, apply the state before clearing
+    pub fn apply(&self, gl : &gl::Gl) {
+        gl.set_enabled(gl::DEPTH_TEST, self.depth.test);
+        gl.depth_mask(self.depth.write);
+        if self.depth.test {
+            gl.depth_func(self.depth.func);
+        }
+
+        match self.blend.factors() {
+            Some((source, destination)) => {
+                gl.enable(gl::BLEND);
+                gl.blend_equation(gl::FUNC_ADD);
+                gl.blend_func_separate(source, destination, gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+            }
+            None => gl.disable(gl::BLEND),
+        }
+
+        match self.cull {
+            CullMode::None => gl.disable(gl::CULL_FACE),
+            CullMode::Back | CullMode::Front => {
+                gl.enable(gl::CULL_FACE);
+                gl.cull_face(if self.cull == CullMode::Back { gl::BACK } else { gl::FRONT });
+            }
+        }
+        gl.front_face(self.front_face);
+
+        match self.stencil {
+            Some(stencil) => {
+                gl.enable(gl::STENCIL_TEST);
+                gl.stencil_func(stencil.func, stencil.reference, stencil.read_mask);
+                gl.stencil_op(stencil.stencil_fail, stencil.depth_fail, stencil.pass);
+                gl.stencil_mask(stencil.write_mask);
+            }
+            None => {
+                gl.disable(gl::STENCIL_TEST);
+                // Let glClear reset the whole stencil buffer
+                gl.stencil_mask(0xFF);
+            }
+        }
+
+        gl.polygon_mode(self.polygon_mode.to_gl());
+
+        match self.scissor {
+            Some(scissor) => {
+                gl.enable(gl::SCISSOR_TEST);
+                gl.scissor(scissor.x, scissor.y, scissor.width, scissor.height);
+            }
+            None => gl.disable(gl::SCISSOR_TEST),
+        }
+    }
+}
@@ -13,6 +13,7 @@ pub enum Error {
     Io(io::Error),
     FileContainsNil,
     FailedToGetExePath,
+    Image(image::ImageError),
 }
 
 impl From<io::Error> for Error {
@@ -52,6 +53,35 @@ impl Resources {
 
         Ok(unsafe { ffi::CString::from_vec_unchecked(buffer) })
     }
+
+    /// Path on disk of a resource, for libraries that open files by themselves
+    pub fn path(&self, resource_name: &str) -> PathBuf {
+        resource_name_to_path(&self.root_path, resource_name)
+    }
+
+    pub fn load_string(&self, resource_name: &str) -> Result<String, Error> {
+        let path = resource_name_to_path(&self.root_path, resource_name);
+        Ok(fs::read_to_string(path)?)
+    }
+
+    pub fn load_image(&self, resource_name: &str) -> Result<image::RgbaImage, Error> {
+        let path = resource_name_to_path(&self.root_path, resource_name);
+        let img = image::open(path).map_err(Error::Image)?;
+
+        // OpenGL expects the first row to be the bottom of the image
+        Ok(img.flipv().to_rgba8())
+    }
+}
+
+/// Resolves a resource name referenced from inside another resource
+/// (e.g. a texture inside a material file) relative to that resource's folder
+pub fn relative_resource_name(base_name: &str, location: &str) -> String {
+    let location = location.replace('\\', "/");
+
+    match base_name.rfind('/') {
+        Some(index) => format!("{}/{}", &base_name[..index], location),
+        None => location,
+    }
 }
 
 fn resource_name_to_path(root_dir: &Path, location: &str) -> PathBuf {
@@ -0,0 +1,549 @@
+use nalgebra::{Isometry3, Matrix4, Point3, Translation3, UnitQuaternion, Vector3};
+
+use crate::graphics::frustum::Frustum;
+use crate::graphics::gltf_import::GltfScene;
+use crate::graphics::mesh::{Mesh, MeshData, MAX_MORPH_TARGETS};
+use crate::graphics::program::Program;
+use crate::graphics::ray::Ray;
+use crate::graphics::skin::{Skeleton, JOINTS_BINDING};
+use crate::graphics::ubo::UBO;
+
+/// Local transform of a node relative to its parent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub isometry: Isometry3<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {
+            isometry: Isometry3::identity(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn new(translation: Vector3<f32>, rotation: UnitQuaternion<f32>, scale: Vector3<f32>) -> Transform {
+        Transform {
+            isometry: Isometry3::from_parts(Translation3::from(translation), rotation),
+            scale,
+        }
+    }
+
+    pub fn from_translation(translation: Vector3<f32>) -> Transform {
+        Transform::new(translation, UnitQuaternion::identity(), Vector3::new(1.0, 1.0, 1.0))
+    }
+
+    /// Scale first, then rotation and translation
+    pub fn matrix(&self) -> Matrix4<f32> {
+        self.isometry.to_homogeneous() * Matrix4::new_nonuniform_scaling(&self.scale)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+pub struct Node {
+    pub name: String,
+    /// Indices into the mesh list given to `SceneGraph::render`
+    pub meshes: Vec<usize>,
+    /// Index of the skeleton deforming the meshes, see `SceneGraph::render_skinned`
+    pub skin: Option<usize>,
+    /// Weights of the morph targets of the meshes
+    pub morph_weights: Vec<f32>,
+    transform: Transform,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+
+    world: Matrix4<f32>,
+    dirty: bool,
+}
+
+impl Node {
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+/// Hierarchy of nodes whose world matrices are only recomputed
+/// when their transform, or the transform of one of their ancestors, changes
+pub struct SceneGraph {
+    nodes: Vec<Node>,
+    roots: Vec<NodeId>,
+}
+
+impl Default for SceneGraph {
+    fn default() -> SceneGraph {
+        SceneGraph::new()
+    }
+}
+
+impl SceneGraph {
+    pub fn new() -> SceneGraph {
+        SceneGraph {
+            nodes: Vec::new(),
+            roots: Vec::new(),
+        }
+    }
+
+    /// Builds the hierarchy of one of the scenes of a glTF file, `None` for its default scene (or the first one)
+    /// Mesh indices follow the order of `GltfScene::upload_meshes`
+    /// and node indices the order of `GltfScene::nodes` (see `node_at`)
+    /// Every node is added, the ones belonging to other scenes are detached and not rendered
+    pub fn from_gltf(scene: &GltfScene, scene_index: Option<usize>) -> Result<SceneGraph, String> {
+        let roots = match scene_index.or(scene.default_scene) {
+            Some(index) => scene.scenes.get(index).ok_or_else(|| format!("no scene {} in the file", index))?,
+            None => scene.scenes.first().map_or(&[][..], Vec::as_slice),
+        };
+
+        let mut graph = SceneGraph::new();
+
+        let mut first_primitive = Vec::with_capacity(scene.meshes.len());
+        let mut primitive_count = 0;
+        for group in &scene.meshes {
+            first_primitive.push(primitive_count);
+            primitive_count += group.primitives.len();
+        }
+
+        let mut ids = Vec::with_capacity(scene.nodes.len());
+        for data in &scene.nodes {
+            let id = graph.add_node(&data.name, Transform::new(data.translation, data.rotation, data.scale), None);
+
+            if let Some(mesh) = data.mesh {
+                let first = first_primitive[mesh];
+                graph.nodes[id.0].meshes = (first..first + scene.meshes[mesh].primitives.len()).collect();
+            }
+
+            graph.nodes[id.0].skin = data.skin;
+            graph.nodes[id.0].morph_weights = match (&data.weights, data.mesh) {
+                (Some(weights), _) => weights.clone(),
+                (None, Some(mesh)) => scene.meshes[mesh].weights.clone(),
+                (None, None) => Vec::new(),
+            };
+
+            ids.push(id);
+        }
+
+        for (index, data) in scene.nodes.iter().enumerate() {
+            for &child in &data.children {
+                graph.set_parent(ids[child], Some(ids[index]))
+                    .map_err(|error| format!("node '{}': {}", data.name, error))?;
+            }
+        }
+
+        let roots: Vec<NodeId> = roots.iter().map(|&root| ids[root]).collect();
+        graph.roots.retain(|root| roots.contains(root));
+
+        Ok(graph)
+    }
+
+    pub fn add_node(&mut self, name: &str, transform: Transform, parent: Option<NodeId>) -> NodeId {
+        let id = NodeId(self.nodes.len());
+
+        self.nodes.push(Node {
+            name: String::from(name),
+            meshes: Vec::new(),
+            skin: None,
+            morph_weights: Vec::new(),
+            transform,
+            parent: None,
+            children: Vec::new(),
+            world: Matrix4::identity(),
+            dirty: true,
+        });
+
+        // A new node has no children, it can't be an ancestor of its parent
+        match parent {
+            Some(parent) => self.nodes[parent.0].children.push(id),
+            None => self.roots.push(id),
+        }
+        self.nodes[id.0].parent = parent;
+
+        id
+    }
+
+    pub fn add_mesh_node(&mut self, name: &str, transform: Transform, parent: Option<NodeId>, mesh: usize) -> NodeId {
+        let id = self.add_node(name, transform, parent);
+        self.nodes[id.0].meshes.push(mesh);
+        id
+    }
+
+    /// Nodes are numbered in the order they were added
+    pub fn node_at(&self, index: usize) -> NodeId {
+        assert!(index < self.nodes.len(), "no node at index {}", index);
+        NodeId(index)
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &Node)> {
+        self.nodes.iter().enumerate().map(|(index, node)| (NodeId(index), node))
+    }
+
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes.iter().position(|node| node.name == name).map(NodeId)
+    }
+
+    /// Moves the node and its descendants under `parent`, or makes it a root
+    /// Fails if `parent` is the node or one of its descendants
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> Result<(), String> {
+        if let Some(parent) = parent {
+            if self.is_ancestor(id, parent) {
+                return Err(format!("'{}' can not be parented to its own descendant '{}'",
+                                   self.nodes[id.0].name, self.nodes[parent.0].name));
+            }
+        }
+
+        if let Some(old_parent) = self.nodes[id.0].parent {
+            self.nodes[old_parent.0].children.retain(|&child| child != id);
+        } else {
+            self.roots.retain(|&root| root != id);
+        }
+
+        match parent {
+            Some(parent) => self.nodes[parent.0].children.push(id),
+            None => self.roots.push(id),
+        }
+
+        self.nodes[id.0].parent = parent;
+        self.mark_dirty(id);
+
+        Ok(())
+    }
+
+    /// Returns true if `ancestor` is `id` or one of its parents
+    fn is_ancestor(&self, ancestor: NodeId, mut id: NodeId) -> bool {
+        loop {
+            if id == ancestor {
+                return true;
+            }
+
+            match self.nodes[id.0].parent {
+                Some(parent) => id = parent,
+                None => return false,
+            }
+        }
+    }
+
+    pub fn set_morph_weights(&mut self, id: NodeId, weights: &[f32]) {
+        self.nodes[id.0].morph_weights = weights.to_vec();
+    }
+
+    pub fn set_transform(&mut self, id: NodeId, transform: Transform) {
+        self.nodes[id.0].transform = transform;
+        self.mark_dirty(id);
+    }
+
+    /// Changes the transform in place and invalidates the node and its descendants
+    pub fn update_transform<F: FnOnce(&mut Transform)>(&mut self, id: NodeId, update: F) {
+        update(&mut self.nodes[id.0].transform);
+        self.mark_dirty(id);
+    }
+
+    fn mark_dirty(&mut self, id: NodeId) {
+        let mut stack = vec![id];
+
+        while let Some(id) = stack.pop() {
+            let node = &mut self.nodes[id.0];
+
+            // Descendants of a dirty node are already dirty
+            if node.dirty {
+                continue;
+            }
+
+            node.dirty = true;
+            stack.extend_from_slice(&node.children);
+        }
+    }
+
+    /// Recomputes the world matrices of the dirty nodes
+    pub fn update_world_matrices(&mut self) {
+        let mut stack: Vec<(NodeId, Matrix4<f32>)> = self.roots.iter()
+            .map(|&root| (root, Matrix4::identity()))
+            .collect();
+
+        while let Some((id, parent_world)) = stack.pop() {
+            let node = &mut self.nodes[id.0];
+
+            if node.dirty {
+                node.world = parent_world * node.transform.matrix();
+                node.dirty = false;
+            }
+
+            let world = node.world;
+            for &child in &node.children {
+                stack.push((child, world));
+            }
+        }
+    }
+
+    /// World matrix computed by the last `update_world_matrices`
+    pub fn world_matrix(&self, id: NodeId) -> Matrix4<f32> {
+        self.nodes[id.0].world
+    }
+
+    /// Draws every node with meshes, uploading its world matrix to the "model" uniform
+    /// Skinned nodes are left to `render_skinned`
+    pub fn render(&mut self, shader_program: &Program, meshes: &[Mesh]) {
+        self.render_nodes(shader_program, meshes, None);
+    }
+
+    /// Same as `render` but skips the meshes whose bounds are outside the frustum
+    /// Returns how many meshes were culled
+    pub fn render_visible(&mut self, shader_program: &Program, meshes: &[Mesh], frustum: &Frustum) -> usize {
+        self.render_nodes(shader_program, meshes, Some(frustum))
+    }
+
+    fn render_nodes(&mut self, shader_program: &Program, meshes: &[Mesh], frustum: Option<&Frustum>) -> usize {
+        self.update_world_matrices();
+
+        let mut culled = 0;
+
+        for id in self.visit() {
+            let node = &self.nodes[id.0];
+
+            if node.meshes.is_empty() || node.skin.is_some() {
+                continue;
+            }
+
+            let visible: Vec<&Mesh> = node.meshes.iter()
+                .map(|&mesh| &meshes[mesh])
+                .filter(|mesh| match (frustum, &mesh.bounds) {
+                    (Some(frustum), Some(bounds)) => frustum.intersects_aabb(&bounds.transform(&node.world)),
+                    _ => true,
+                })
+                .collect();
+
+            culled += node.meshes.len() - visible.len();
+
+            if visible.is_empty() {
+                continue;
+            }
+
+            shader_program.set_matrix4("model", &node.world);
+            upload_morph_weights(shader_program, node);
+
+            for mesh in visible {
+                mesh.draw();
+            }
+        }
+
+        culled
+    }
+
+    /// Closest node hit by the ray with the distance along it
+    /// `meshes` is the CPU side of the mesh list given to `render`, skinned nodes are ignored
+    pub fn pick(&mut self, ray: &Ray, meshes: &[MeshData]) -> Option<(NodeId, f32)> {
+        self.update_world_matrices();
+
+        let mut closest: Option<(NodeId, f32)> = None;
+
+        for id in self.visit() {
+            let node = &self.nodes[id.0];
+
+            if node.meshes.is_empty() || node.skin.is_some() {
+                continue;
+            }
+
+            let inverse_world = match node.world.try_inverse() {
+                Some(inverse) => inverse,
+                None => continue,
+            };
+            let local_ray = ray.transform(&inverse_world);
+
+            for &mesh in &node.meshes {
+                let data = &meshes[mesh];
+
+                // Skip the triangles when the bounds are missed or further than the closest hit
+                let bounds_distance = match data.bounds().and_then(|bounds| local_ray.intersect_aabb(&bounds)) {
+                    Some(distance) => distance,
+                    None => continue,
+                };
+                if closest.is_some_and(|(_, distance)| bounds_distance > distance) {
+                    continue;
+                }
+
+                for triangle in data.indices.chunks(3) {
+                    let a = Point3::from(data.position(triangle[0] as usize));
+                    let b = Point3::from(data.position(triangle[1] as usize));
+                    let c = Point3::from(data.position(triangle[2] as usize));
+
+                    if let Some(distance) = local_ray.intersect_triangle(&a, &b, &c) {
+                        if closest.is_none_or(|(_, closest_distance)| distance < closest_distance) {
+                            closest = Some((id, distance));
+                        }
+                    }
+                }
+            }
+        }
+
+        closest
+    }
+
+    /// Draws the skinned nodes, uploading the joint matrices of their skeleton before each one
+    /// The program's `Joints` block has to be bound to `skin::JOINTS_BINDING`
+    pub fn render_skinned(&mut self, shader_program: &Program, meshes: &[Mesh], skeletons: &[Skeleton], joint_buffer: &UBO) {
+        self.update_world_matrices();
+
+        joint_buffer.bind_base(JOINTS_BINDING);
+
+        for id in self.visit() {
+            let node = &self.nodes[id.0];
+
+            let skin = match node.skin {
+                Some(skin) if !node.meshes.is_empty() => skin,
+                _ => continue,
+            };
+
+            skeletons[skin].upload(self, id, joint_buffer);
+            shader_program.set_matrix4("model", &node.world);
+            upload_morph_weights(shader_program, node);
+
+            for &mesh in &node.meshes {
+                meshes[mesh].draw();
+            }
+        }
+    }
+
+    /// Every node reachable from the roots, parents before their children
+    fn visit(&self) -> Vec<NodeId> {
+        let mut visited = Vec::with_capacity(self.nodes.len());
+        let mut stack: Vec<NodeId> = self.roots.clone();
+
+        while let Some(id) = stack.pop() {
+            visited.push(id);
+            stack.extend_from_slice(&self.nodes[id.0].children);
+        }
+
+        visited
+    }
+}
+
+/// Sets the "morphWeights" uniform, zero for the targets the node doesn't weight
+fn upload_morph_weights(shader_program: &Program, node: &Node) {
+    let mut weights = [0.0f32; MAX_MORPH_TARGETS];
+
+    for (weight, value) in weights.iter_mut().zip(&node.morph_weights) {
+        *weight = *value;
+    }
+
+    shader_program.set_float_array("morphWeights", &weights);
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Point3, UnitQuaternion, Vector3};
+
+    use super::{NodeId, SceneGraph, Transform};
+
+    fn world_position(scene: &SceneGraph, id: NodeId) -> Point3<f32> {
+        scene.world_matrix(id).transform_point(&Point3::origin())
+    }
+
+    fn assert_close(a: Point3<f32>, b: Point3<f32>) {
+        assert!((a - b).norm() < 1.0e-5, "{} is not {}", a, b);
+    }
+
+    /// root at x = 1, child 2 further, grandchild 3 further
+    fn chain() -> (SceneGraph, NodeId, NodeId, NodeId) {
+        let mut scene = SceneGraph::new();
+        let root = scene.add_node("root", Transform::from_translation(Vector3::new(1.0, 0.0, 0.0)), None);
+        let child = scene.add_node("child", Transform::from_translation(Vector3::new(2.0, 0.0, 0.0)), Some(root));
+        let grandchild = scene.add_node("grandchild", Transform::from_translation(Vector3::new(3.0, 0.0, 0.0)), Some(child));
+        scene.update_world_matrices();
+
+        (scene, root, child, grandchild)
+    }
+
+    #[test]
+    fn world_matrices_combine_the_ancestors() {
+        let (scene, _, child, grandchild) = chain();
+
+        assert_close(world_position(&scene, child), Point3::new(3.0, 0.0, 0.0));
+        assert_close(world_position(&scene, grandchild), Point3::new(6.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn changes_reach_the_descendants() {
+        let (mut scene, root, child, grandchild) = chain();
+        assert!(scene.nodes.iter().all(|node| !node.dirty));
+
+        scene.set_transform(root, Transform::from_translation(Vector3::new(0.0, 5.0, 0.0)));
+        assert!(scene.node(child).dirty && scene.node(grandchild).dirty);
+
+        scene.update_world_matrices();
+        assert!(scene.nodes.iter().all(|node| !node.dirty));
+        assert_close(world_position(&scene, grandchild), Point3::new(5.0, 5.0, 0.0));
+    }
+
+    #[test]
+    fn only_the_changed_branch_is_dirty() {
+        let (mut scene, root, child, grandchild) = chain();
+        let sibling = scene.add_node("sibling", Transform::identity(), Some(root));
+        scene.update_world_matrices();
+
+        scene.update_transform(child, |transform| transform.scale = Vector3::new(2.0, 2.0, 2.0));
+
+        assert!(!scene.node(root).dirty && !scene.node(sibling).dirty);
+        assert!(scene.node(child).dirty && scene.node(grandchild).dirty);
+    }
+
+    #[test]
+    fn reparenting_keeps_the_local_transform() {
+        let (mut scene, root, child, grandchild) = chain();
+
+        scene.set_parent(grandchild, Some(root)).unwrap();
+        scene.update_world_matrices();
+
+        assert_eq!(scene.node(child).children(), &[]);
+        assert_eq!(scene.node(root).children(), &[child, grandchild]);
+        assert_eq!(scene.node(grandchild).parent(), Some(root));
+        assert_close(world_position(&scene, grandchild), Point3::new(4.0, 0.0, 0.0));
+
+        scene.set_parent(grandchild, None).unwrap();
+        scene.update_world_matrices();
+
+        assert!(scene.roots.contains(&grandchild));
+        assert_close(world_position(&scene, grandchild), Point3::new(3.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn cycles_are_refused() {
+        let (mut scene, root, child, grandchild) = chain();
+
+        assert!(scene.set_parent(root, Some(grandchild)).is_err());
+        assert!(scene.set_parent(child, Some(child)).is_err());
+        // Nothing changed
+        assert_eq!(scene.node(root).parent(), None);
+        assert_eq!(scene.node(root).children(), &[child]);
+        assert_eq!(scene.roots, vec![root]);
+    }
+
+    #[test]
+    fn scale_then_rotation() {
+        let mut scene = SceneGraph::new();
+        let quarter_turn = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 90.0f32.to_radians());
+        let parent = scene.add_node("parent", Transform::new(Vector3::new(0.0, 0.0, 1.0), quarter_turn, Vector3::new(2.0, 3.0, 1.0)), None);
+        let child = scene.add_node("child", Transform::from_translation(Vector3::new(1.0, 1.0, 0.0)), Some(parent));
+        scene.update_world_matrices();
+
+        // (1, 1) scaled to (2, 3), turned to (-3, 2) then moved up
+        assert_close(world_position(&scene, child), Point3::new(-3.0, 2.0, 1.0));
+    }
+}
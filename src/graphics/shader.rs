@@ -2,7 +2,8 @@ use std::ffi::{CStr};
 use gl::types::GLuint;
 
 use crate::graphics::shader_from_source;
-use crate::Resources;
+use crate::graphics::resources::Resources;
+use crate::graphics::debug;
 
 pub struct Shader {
     gl : gl::Gl,
@@ -15,6 +16,10 @@ impl Shader {
         Ok(Shader { gl : gl.clone() ,id })
     }
 
+    pub fn set_label(&self, label : &str){
+        debug::object_label(&self.gl, gl::SHADER, self.id, label);
+    }
+
     pub fn id(&self) -> GLuint {
         self.id
     }
@@ -43,7 +48,10 @@ impl Shader {
         let source = res.load_cstring(name)
             .map_err(|e| format!("Error loading resource {}: {:?}", name, e))?;
 
-        Shader::from_source(gl, &source, shader_kind)
+        let shader = Shader::from_source(gl, &source, shader_kind)?;
+        shader.set_label(name);
+
+        Ok(shader)
     }
 
 }
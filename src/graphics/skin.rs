@@ -0,0 +1,58 @@
+use gl::types::GLuint;
+use nalgebra::Matrix4;
+
+use crate::graphics::scene::{NodeId, SceneGraph};
+use crate::graphics::ubo::UBO;
+
+/// Must match the size of the `Joints` uniform block in skinned.vert
+pub const MAX_JOINTS: usize = 128;
+
+/// Binding point of the `Joints` uniform block
+pub const JOINTS_BINDING: GLuint = 0;
+
+/// Skin as described by the model file, joints are node indices
+#[derive(Debug, Clone)]
+pub struct Skin {
+    pub name: String,
+    pub joints: Vec<usize>,
+    pub inverse_bind_matrices: Vec<Matrix4<f32>>,
+}
+
+/// Skin bound to the nodes of a scene graph
+pub struct Skeleton {
+    pub joints: Vec<NodeId>,
+    pub inverse_bind_matrices: Vec<Matrix4<f32>>,
+}
+
+impl Skeleton {
+    /// `node_id` maps the skin's node indices to scene nodes
+    pub fn from_skin<F: Fn(usize) -> NodeId>(skin: &Skin, node_id: F) -> Skeleton {
+        Skeleton {
+            joints: skin.joints.iter().map(|&joint| node_id(joint)).collect(),
+            inverse_bind_matrices: skin.inverse_bind_matrices.clone(),
+        }
+    }
+
+    /// Matrices taking a vertex from bind pose to the current pose,
+    /// in the space of the node the skinned mesh is attached to
+    pub fn joint_matrices(&self, scene: &SceneGraph, mesh_node: NodeId) -> Vec<Matrix4<f32>> {
+        let inverse_mesh_world = scene.world_matrix(mesh_node)
+            .try_inverse()
+            .unwrap_or_else(Matrix4::identity);
+
+        self.joints.iter()
+            .zip(&self.inverse_bind_matrices)
+            .map(|(&joint, inverse_bind)| inverse_mesh_world * scene.world_matrix(joint) * inverse_bind)
+            .collect()
+    }
+
+    /// Writes the joint matrices into a buffer created by `joint_buffer`
+    pub fn upload(&self, scene: &SceneGraph, mesh_node: NodeId, buffer: &UBO) {
+        buffer.update(&self.joint_matrices(scene, mesh_node));
+    }
+}
+
+/// Uniform buffer big enough for the joint matrices of any skeleton
+pub fn joint_buffer(gl: &gl::Gl) -> UBO {
+    UBO::new(gl, MAX_JOINTS * std::mem::size_of::<Matrix4<f32>>())
+}
@@ -1,10 +1,9 @@
 use std::ffi::CString;
-use std::path::Path;
-use gl::types::{GLenum, GLfloat, GLint, GLsizei, GLuint};
+use gl::types::{GLenum, GLint, GLuint};
 
-use image::io::Reader as ImageReader;
 use image::RgbaImage;
 use crate::graphics::program::Program;
+use crate::graphics::debug;
 
 pub struct Texture {
     gl : gl::Gl,
@@ -12,14 +11,46 @@ pub struct Texture {
     pub texture_type : GLenum,
 }
 
+/// Filtering and wrapping applied when sampling a texture
+#[derive(Debug, Clone, Copy)]
+pub struct Sampler {
+    pub min_filter : GLenum,
+    pub mag_filter : GLenum,
+    pub wrap_s : GLenum,
+    pub wrap_t : GLenum,
+}
+
+impl Default for Sampler {
+    fn default() -> Sampler {
+        Sampler {
+            min_filter : gl::LINEAR,
+            mag_filter : gl::LINEAR,
+            wrap_s : gl::REPEAT,
+            wrap_t : gl::REPEAT,
+        }
+    }
+}
+
+impl Sampler {
+    pub fn uses_mipmaps(&self) -> bool {
+        matches!(
+            self.min_filter,
+            gl::NEAREST_MIPMAP_NEAREST | gl::LINEAR_MIPMAP_NEAREST | gl::NEAREST_MIPMAP_LINEAR | gl::LINEAR_MIPMAP_LINEAR
+        )
+    }
+}
+
 impl Texture {
     pub fn new(gl : &gl::Gl, img: &RgbaImage, texture_type : GLenum) -> Texture {
-        // let img = ImageReader::open(file).unwrap().decode().unwrap().flipv().to_rgba8();
+        Texture::with_sampler(gl, img, texture_type, &Sampler::default())
+    }
+
+    pub fn with_sampler(gl : &gl::Gl, img: &RgbaImage, texture_type : GLenum, sampler : &Sampler) -> Texture {
         let mut texture_id = 0;
 
         unsafe {
             gl.GenTextures(1, &mut texture_id);
-            gl.BindTexture(gl::TEXTURE_2D, texture_id);
+            gl.bind_texture(gl::TEXTURE_2D, texture_id);
 
             gl.TexImage2D(
                 gl::TEXTURE_2D,
@@ -33,10 +64,16 @@ impl Texture {
                 img.as_raw().as_ptr() as *const _
             );
 
-            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, sampler.min_filter as i32);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, sampler.mag_filter as i32);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, sampler.wrap_s as i32);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, sampler.wrap_t as i32);
+
+            if sampler.uses_mipmaps() {
+                gl.GenerateMipmap(gl::TEXTURE_2D);
+            }
 
-            gl.BindTexture(gl::TEXTURE_2D, 0);
+            gl.bind_texture(gl::TEXTURE_2D, 0);
         }
 
         Texture {
@@ -58,20 +95,29 @@ impl Texture {
     }
 
     pub fn bind(&self){
-        unsafe {
-            self.gl.BindTexture(self.texture_type, self.id);
-        }
+        self.gl.bind_texture(self.texture_type, self.id);
+    }
+
+    /// Binds to texture unit `unit` (0 for GL_TEXTURE0), the one a sampler uniform set to `unit` reads
+    pub fn bind_unit(&self, unit : GLuint){
+        self.gl.bind_texture_unit(unit, self.texture_type, self.id);
     }
 
     pub fn unbind(&self){
-        unsafe {
-            self.gl.BindTexture(self.texture_type, 0);
-        }
+        self.gl.bind_texture(self.texture_type, 0);
+    }
+
+    /// Hands the texture over to another context of the share group, its filtering and wrapping come along
+    pub fn adopt(&mut self, gl : &gl::Gl){
+        debug_assert!(self.gl.shares_objects_with(gl), "texture {} is not shared with GL context {}", self.id, gl.context_id());
+        self.gl = gl.clone();
+    }
+
+    pub fn set_label(&self, label : &str){
+        debug::object_label(&self.gl, gl::TEXTURE, self.id, label);
     }
 
     pub fn delete(&self){
-        unsafe {
-            self.gl.DeleteTextures(1, &self.id);
-        }
+        self.gl.delete_texture(self.id);
     }
 }
\ No newline at end of file
@@ -0,0 +1,65 @@
+use gl::types::{GLintptr, GLsizeiptr, GLuint};
+
+/// Uniform buffer object, shared by every program bound to the same binding point
+pub struct UBO {
+    gl : gl::Gl,
+    id: GLuint,
+    size: usize,
+}
+
+impl UBO {
+    /// Allocates `size` bytes that are meant to be updated often
+    pub fn new(gl : &gl::Gl, size : usize) -> UBO{
+        let mut ubo : GLuint = 0;
+
+        unsafe { gl.GenBuffers(1, &mut ubo); }
+
+        gl.bind_buffer(gl::UNIFORM_BUFFER, ubo);
+        unsafe {
+            gl.BufferData(
+                gl::UNIFORM_BUFFER,
+                size as GLsizeiptr,
+                std::ptr::null(),
+                gl::DYNAMIC_DRAW
+            );
+        }
+        gl.bind_buffer(gl::UNIFORM_BUFFER, 0);
+
+        UBO{
+            gl : gl.clone(),
+            id : ubo,
+            size,
+        }
+    }
+
+    /// Writes `data` at the start of the buffer, the data must follow the std140 layout
+    pub fn update<T: Copy>(&self, data : &[T]){
+        let bytes = std::mem::size_of_val(data);
+        assert!(bytes <= self.size, "{} bytes don't fit in a uniform buffer of {} bytes", bytes, self.size);
+
+        self.gl.bind_buffer(gl::UNIFORM_BUFFER, self.id);
+        unsafe {
+            self.gl.BufferSubData(
+                gl::UNIFORM_BUFFER,
+                0 as GLintptr,
+                bytes as GLsizeiptr,
+                data.as_ptr() as *const gl::types::GLvoid
+            );
+        }
+    }
+
+    /// Hands the block over to another context of the share group, binding points are per context so bind it again there
+    pub fn adopt(&mut self, gl : &gl::Gl){
+        debug_assert!(self.gl.shares_objects_with(gl), "buffer {} is not shared with GL context {}", self.id, gl.context_id());
+        self.gl = gl.clone();
+    }
+
+    /// Makes the buffer visible to the uniform blocks bound to `binding`
+    pub fn bind_base(&self, binding : GLuint){
+        self.gl.bind_buffer_base(gl::UNIFORM_BUFFER, binding, self.id);
+    }
+
+    pub fn delete(&self){
+        self.gl.delete_buffer(self.id);
+    }
+}
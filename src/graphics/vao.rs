@@ -1,5 +1,6 @@
-use gl::types::{GLuint, GLint, GLsizei, GLvoid};
+use gl::types::{GLenum, GLuint, GLint, GLsizei, GLvoid};
 use crate::graphics::vbo::VBO;
+use crate::graphics::debug;
 
 pub struct VAO {
     gl : gl::Gl,
@@ -20,12 +21,13 @@ impl VAO {
 
     }
 
+    /// `offset` is the position in bytes of the attribute in the vertex
     pub fn link_attrib(&self,
                        vbo : &VBO,
                        layout : GLuint,
                        size : GLint,
                        stride : GLsizei,
-                       pointer : *const GLvoid){
+                       offset : usize){
         vbo.bind();
         unsafe {
             self.gl.EnableVertexAttribArray(layout);
@@ -36,23 +38,46 @@ impl VAO {
                 gl::FLOAT,
                 gl::FALSE,
                 stride,
-                pointer
+                offset as *const GLvoid
             );
         }
-        vbo.unbind()
+    }
+
+    /// Links an integer attribute (e.g. joint indices), read as ivec/uvec in the shader
+    pub fn link_attrib_i(&self,
+                         vbo : &VBO,
+                         layout : GLuint,
+                         size : GLint,
+                         kind : GLenum,
+                         stride : GLsizei,
+                         offset : usize){
+        vbo.bind();
+        unsafe {
+            self.gl.EnableVertexAttribArray(layout);
 
+            self.gl.VertexAttribIPointer(
+                layout,
+                size,
+                kind,
+                stride,
+                offset as *const GLvoid
+            );
+        }
+    }
 
+    pub fn set_label(&self, label : &str){
+        debug::object_label(&self.gl, gl::VERTEX_ARRAY, self.id, label);
     }
 
     pub fn bind(&self){
-        unsafe { self.gl.BindVertexArray(self.id); }
+        self.gl.bind_vertex_array(self.id);
     }
 
     pub fn unbind(&self){
-        unsafe { self.gl.BindVertexArray(0); }
+        self.gl.bind_vertex_array(0);
     }
 
     pub fn delete(&self){
-        unsafe { self.gl.DeleteVertexArrays(1, &self.id); }
+        self.gl.delete_vertex_array(self.id);
     }
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
 use gl;
 use gl::types::{GLuint, GLsizeiptr};
+use crate::graphics::debug;
 
 pub struct VBO {
     gl : gl::Gl,
@@ -7,17 +8,22 @@ pub struct VBO {
 }
 
 impl VBO {
-    pub fn new(gl : &gl::Gl, vertices : &Vec<f32>) -> VBO{
+    pub fn new(gl : &gl::Gl, vertices : &[f32]) -> VBO{
+        VBO::from_slice(gl, vertices)
+    }
+
+    /// Uploads any plain data (e.g. `[u16; 4]` joint indices) as a vertex buffer
+    pub fn from_slice<T: Copy>(gl : &gl::Gl, data : &[T]) -> VBO{
         let mut vbo : GLuint = 0;
 
-        unsafe {
-            gl.GenBuffers(1, &mut vbo);
+        unsafe { gl.GenBuffers(1, &mut vbo); }
 
-            gl.BindBuffer(gl::ARRAY_BUFFER, vbo);
+        gl.bind_buffer(gl::ARRAY_BUFFER, vbo);
+        unsafe {
             gl.BufferData(
                 gl::ARRAY_BUFFER,
-                (vertices.len() * std::mem::size_of::<f32>()) as GLsizeiptr,
-                vertices.as_ptr() as *const gl::types::GLvoid,
+                std::mem::size_of_val(data) as GLsizeiptr,
+                data.as_ptr() as *const gl::types::GLvoid,
                 gl::STATIC_DRAW
             );
         }
@@ -28,15 +34,39 @@ impl VBO {
         }
     }
 
+    /// Replaces the whole content, e.g. for vertices rebuilt every frame
+    pub fn update<T: Copy>(&self, data : &[T]){
+        self.bind();
+        unsafe {
+            self.gl.BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(data) as GLsizeiptr,
+                data.as_ptr() as *const gl::types::GLvoid,
+                gl::DYNAMIC_DRAW
+            );
+        }
+        self.unbind();
+    }
+
+    /// Hands the vertices over to another context of the share group, link them in a vertex array created there
+    pub fn adopt(&mut self, gl : &gl::Gl){
+        debug_assert!(self.gl.shares_objects_with(gl), "buffer {} is not shared with GL context {}", self.id, gl.context_id());
+        self.gl = gl.clone();
+    }
+
+    pub fn set_label(&self, label : &str){
+        debug::object_label(&self.gl, gl::BUFFER, self.id, label);
+    }
+
     pub fn bind(&self){
-        unsafe { self.gl.BindBuffer(gl::ARRAY_BUFFER, self.id); }
+        self.gl.bind_buffer(gl::ARRAY_BUFFER, self.id);
     }
 
     pub fn unbind(&self){
-        unsafe { self.gl.BindBuffer(gl::ARRAY_BUFFER, 0); }
+        self.gl.bind_buffer(gl::ARRAY_BUFFER, 0);
     }
 
     pub fn delete(&self){
-        unsafe { self.gl.DeleteBuffers(1, &self.id); }
+        self.gl.delete_buffer(self.id);
     }
 }
\ No newline at end of file
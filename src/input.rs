@@ -0,0 +1,698 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use sdl2::controller::{Axis, Button};
+use sdl2::event::Event;
+use sdl2::keyboard::Scancode;
+use sdl2::mouse::MouseButton;
+
+use crate::graphics::resources::{self, Resources};
+
+/// Physical button an action can be bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(Scancode),
+    Mouse(MouseButton),
+    Controller(Button),
+}
+
+/// Values of the SDL_Scancode enum, `Scancode::from_i32` is only valid for them
+const SCANCODES: [RangeInclusive<i32>; 5] = [4..=129, 133..=164, 176..=221, 224..=231, 257..=286];
+
+/// Source of an analog value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AxisBinding {
+    /// -1 while `negative` is held, 1 while `positive` is held
+    Buttons { negative: Binding, positive: Binding },
+    /// Mouse movement since the last frame, in pixels
+    MouseX,
+    MouseY,
+    /// Wheel steps since the last frame
+    MouseWheel,
+    /// Stick or trigger position between -1 and 1 once the dead zone is removed, times `scale`
+    Controller { axis: Axis, scale: f32 },
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Resource { name: String, inner: resources::Error },
+    Parse { file: String, line: usize, message: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Resource { name, inner } => write!(f, "{}: {:?}", name, inner),
+            Error::Parse { file, line, message } => write!(f, "{}:{}: {}", file, line, message),
+        }
+    }
+}
+
+/// Named actions and axes with the inputs bound to them
+#[derive(Debug, Clone, Default)]
+pub struct InputMap {
+    actions: HashMap<String, Vec<Binding>>,
+    axes: HashMap<String, Vec<AxisBinding>>,
+}
+
+impl InputMap {
+    pub fn new() -> InputMap {
+        InputMap::default()
+    }
+
+    /// Bindings used when there is no config file
+    pub fn defaults() -> InputMap {
+        let mut map = InputMap::new();
+
+        map.bind_action("quit", Binding::Key(Scancode::Escape));
+        map.bind_action("pick", Binding::Mouse(MouseButton::Left));
+        map.bind_action("cycle_projection", Binding::Key(Scancode::P));
+        map.bind_action("fps_camera", Binding::Key(Scancode::Num1));
+        map.bind_action("orbit_camera", Binding::Key(Scancode::Num2));
+        map.bind_action("free_camera", Binding::Key(Scancode::Num3));
+        map.bind_action("sprint", Binding::Key(Scancode::LCtrl));
+        map.bind_action("toggle_mouse_capture", Binding::Key(Scancode::Tab));
+        map.bind_action("toggle_mouse_capture", Binding::Mouse(MouseButton::Right));
+        map.bind_action("cycle_window_mode", Binding::Key(Scancode::F11));
+        map.bind_action("next_display", Binding::Key(Scancode::F10));
+        map.bind_action("toggle_profiler", Binding::Key(Scancode::F3));
+        map.bind_action("export_profile", Binding::Key(Scancode::F4));
+        map.bind_action("toggle_wireframe", Binding::Key(Scancode::F2));
+        map.bind_action("camera_tour", Binding::Key(Scancode::T));
+        map.bind_action("camera_home", Binding::Key(Scancode::H));
+        map.bind_action("next_animation", Binding::Key(Scancode::N));
+
+        let keys = |negative, positive| AxisBinding::Buttons {
+            negative: Binding::Key(negative),
+            positive: Binding::Key(positive),
+        };
+        map.bind_axis("move_forward", keys(Scancode::S, Scancode::W));
+        map.bind_axis("move_right", keys(Scancode::A, Scancode::D));
+        map.bind_axis("move_up", keys(Scancode::LShift, Scancode::Space));
+        map.bind_axis("roll", keys(Scancode::E, Scancode::Q));
+        map.bind_axis("look_x", AxisBinding::MouseX);
+        map.bind_axis("look_y", AxisBinding::MouseY);
+        map.bind_axis("zoom", AxisBinding::MouseWheel);
+
+        // Sticks down and right are positive
+        let stick = |axis, scale| AxisBinding::Controller { axis, scale };
+        map.bind_action("quit", Binding::Controller(Button::Back));
+        map.bind_action("pick", Binding::Controller(Button::A));
+        map.bind_action("sprint", Binding::Controller(Button::LeftStick));
+        map.bind_axis("move_forward", stick(Axis::LeftY, -1.0));
+        map.bind_axis("move_right", stick(Axis::LeftX, 1.0));
+        map.bind_axis("move_up", stick(Axis::TriggerRight, 1.0));
+        map.bind_axis("move_up", stick(Axis::TriggerLeft, -1.0));
+        map.bind_axis("turn_x", stick(Axis::RightX, 1000.0));
+        map.bind_axis("turn_y", stick(Axis::RightY, 1000.0));
+
+        map
+    }
+
+    /// Loads the bindings from a config resource, one binding list per line:
+    ///
+    /// ```text
+    /// # comment
+    /// action quit = Key:Escape, Button:back
+    /// axis move_forward = Key:S / Key:W, Axis:lefty * -1
+    /// axis look_x = Mouse:X
+    /// action menu = Key:"Keypad #", Key:"/" # names with '#', ',', '/' or '"' are quoted
+    /// action enter = Scancode:158 # keys without a name of their own
+    /// ```
+    pub fn from_res(res: &Resources, name: &str) -> Result<InputMap, Error> {
+        let source = res.load_string(name)
+            .map_err(|inner| Error::Resource { name: String::from(name), inner })?;
+
+        InputMap::parse(&source, name)
+    }
+
+    /// Parses a config, `file` is only used in the error messages
+    pub fn parse(source: &str, file: &str) -> Result<InputMap, Error> {
+        let mut map = InputMap::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let error = |message: String| Error::Parse { file: String::from(file), line: index + 1, message };
+
+            let line = strip_comment(line).trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let (declaration, bindings) = line.split_once('=')
+                .ok_or_else(|| error(String::from("expected '='")))?;
+
+            let mut declaration = declaration.split_whitespace();
+            let (kind, name) = match (declaration.next(), declaration.next(), declaration.next()) {
+                (Some(kind), Some(name), None) => (kind, name),
+                _ => return Err(error(String::from("expected 'action <name>' or 'axis <name>'"))),
+            };
+
+            let bindings = split_unquoted(bindings, ',').map_err(error)?;
+            let bindings = bindings.into_iter().map(str::trim).filter(|binding| !binding.is_empty());
+
+            match kind {
+                "action" => {
+                    map.actions.entry(String::from(name)).or_default();
+                    for binding in bindings {
+                        map.bind_action(name, parse_binding(binding).map_err(error)?);
+                    }
+                }
+                "axis" => {
+                    map.axes.entry(String::from(name)).or_default();
+                    for binding in bindings {
+                        map.bind_axis(name, parse_axis_binding(binding).map_err(error)?);
+                    }
+                }
+                _ => return Err(error(format!("unknown declaration '{}'", kind))),
+            }
+        }
+
+        Ok(map)
+    }
+
+    /// Writes the bindings back in the config format
+    pub fn to_config(&self) -> String {
+        let mut lines: Vec<String> = Vec::new();
+
+        for (name, bindings) in &self.actions {
+            let bindings: Vec<String> = bindings.iter().map(Binding::to_string).collect();
+            lines.push(format!("action {} = {}", name, bindings.join(", ")));
+        }
+        for (name, bindings) in &self.axes {
+            let bindings: Vec<String> = bindings.iter().map(AxisBinding::to_string).collect();
+            lines.push(format!("axis {} = {}", name, bindings.join(", ")));
+        }
+
+        lines.sort();
+        lines.join("\n") + "\n"
+    }
+
+    pub fn bind_action(&mut self, action: &str, binding: Binding) {
+        self.actions.entry(String::from(action)).or_default().push(binding);
+    }
+
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        self.axes.entry(String::from(axis)).or_default().push(binding);
+    }
+
+    /// Removes every binding of an action or axis, ready to be rebound
+    pub fn unbind(&mut self, name: &str) {
+        self.actions.remove(name);
+        self.axes.remove(name);
+    }
+
+    pub fn action_bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.get(axis).map_or(&[], Vec::as_slice)
+    }
+}
+
+fn parse_binding(binding: &str) -> Result<Binding, String> {
+    let (device, name) = binding.split_once(':')
+        .ok_or_else(|| format!("expected '<device>:<name>', got '{}'", binding))?;
+
+    let name = unquote(name.trim())?;
+    let parsed = match device.trim() {
+        "Key" => Scancode::from_name(&name).map(Binding::Key),
+        "Scancode" => name.parse::<i32>().ok().and_then(scancode_from_i32).map(Binding::Key),
+        "Mouse" => mouse_button_from_name(&name).map(Binding::Mouse),
+        "Button" => Button::from_string(&name).map(Binding::Controller),
+        _ => return Err(format!("unknown device '{}'", device)),
+    };
+
+    parsed.ok_or_else(|| format!("unknown button '{}'", binding))
+}
+
+fn parse_axis_binding(binding: &str) -> Result<AxisBinding, String> {
+    match split_unquoted(binding, '/')?.as_slice() {
+        [_] => { }
+        [negative, positive] => {
+            return Ok(AxisBinding::Buttons {
+                negative: parse_binding(negative.trim())?,
+                positive: parse_binding(positive.trim())?,
+            });
+        }
+        _ => return Err(format!("expected '<negative> / <positive>', got '{}'", binding)),
+    }
+
+    match binding {
+        "Mouse:X" => Ok(AxisBinding::MouseX),
+        "Mouse:Y" => Ok(AxisBinding::MouseY),
+        "Mouse:Wheel" => Ok(AxisBinding::MouseWheel),
+        _ => match binding.strip_prefix("Axis:") {
+            Some(axis) => {
+                // Optional "* <scale>" after the axis name
+                let (name, scale) = match axis.split_once('*') {
+                    Some((name, scale)) => {
+                        let scale = scale.trim().parse::<f32>()
+                            .map_err(|_| format!("invalid scale in '{}'", binding))?;
+                        (name.trim(), scale)
+                    }
+                    None => (axis.trim(), 1.0),
+                };
+
+                Axis::from_string(name)
+                    .map(|axis| AxisBinding::Controller { axis, scale })
+                    .ok_or_else(|| format!("unknown axis '{}'", binding))
+            }
+            None => Err(format!("unknown axis '{}'", binding)),
+        },
+    }
+}
+
+/// The line up to the first '#' outside quotes
+fn strip_comment(line: &str) -> &str {
+    let mut in_quotes = false;
+    let mut escaped = false;
+
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            '#' if !in_quotes => return &line[..index],
+            _ => { }
+        }
+    }
+
+    line
+}
+
+/// Splits at the separators outside quotes
+fn split_unquoted(text: &str, separator: char) -> Result<Vec<&str>, String> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut escaped = false;
+
+    for (index, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            _ if c == separator && !in_quotes => {
+                parts.push(&text[start..index]);
+                start = index + c.len_utf8();
+            }
+            _ => { }
+        }
+    }
+
+    if in_quotes {
+        return Err(format!("unterminated quote in '{}'", text));
+    }
+    parts.push(&text[start..]);
+
+    Ok(parts)
+}
+
+/// Removes the quotes around a name and the backslashes escaping its characters, unquoted names are kept as they are
+fn unquote(name: &str) -> Result<String, String> {
+    let quoted = match name.strip_prefix('"') {
+        Some(quoted) => quoted,
+        None => return Ok(String::from(name)),
+    };
+
+    let mut unquoted = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            '"' if chars.as_str().is_empty() => return Ok(unquoted),
+            '"' => return Err(format!("unexpected characters after '{}'", name)),
+            _ => unquoted.push(c),
+        }
+    }
+
+    Err(format!("unterminated quote in '{}'", name))
+}
+
+/// Quotes the names the config format would otherwise misread, e.g. "Keypad #" or ","
+fn quote(name: &str) -> String {
+    let needs_quotes = name.is_empty()
+        || name.trim() != name
+        || name.contains(['#', ',', '/', '"']);
+
+    if !needs_quotes {
+        return String::from(name);
+    }
+
+    let escaped = name.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{}\"", escaped)
+}
+
+fn scancode_from_i32(value: i32) -> Option<Scancode> {
+    if SCANCODES.iter().any(|range| range.contains(&value)) {
+        Scancode::from_i32(value)
+    } else {
+        None
+    }
+}
+
+fn mouse_button_from_name(name: &str) -> Option<MouseButton> {
+    match name {
+        "Left" => Some(MouseButton::Left),
+        "Middle" => Some(MouseButton::Middle),
+        "Right" => Some(MouseButton::Right),
+        "X1" => Some(MouseButton::X1),
+        "X2" => Some(MouseButton::X2),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(scancode) if Scancode::from_name(scancode.name()) == Some(*scancode) => {
+                write!(f, "Key:{}", quote(scancode.name()))
+            }
+            // No name or the name of another key, e.g. Return2 is also "Return"
+            Binding::Key(scancode) => write!(f, "Scancode:{}", *scancode as i32),
+            Binding::Mouse(button) => write!(f, "Mouse:{:?}", button),
+            Binding::Controller(button) => write!(f, "Button:{}", button.string()),
+        }
+    }
+}
+
+impl fmt::Display for AxisBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AxisBinding::Buttons { negative, positive } => write!(f, "{} / {}", negative, positive),
+            AxisBinding::MouseX => write!(f, "Mouse:X"),
+            AxisBinding::MouseY => write!(f, "Mouse:Y"),
+            AxisBinding::MouseWheel => write!(f, "Mouse:Wheel"),
+            AxisBinding::Controller { axis, scale } if *scale == 1.0 => write!(f, "Axis:{}", axis.string()),
+            AxisBinding::Controller { axis, scale } => write!(f, "Axis:{} * {}", axis.string(), scale),
+        }
+    }
+}
+
+/// Input state of the current frame, queried by action and axis names
+///
+/// Call `begin_frame` before polling the events of a frame and feed each of them to `handle_event`.
+/// The `press`, `release`, `move_mouse`... methods can also be called directly to simulate inputs.
+pub struct Input {
+    pub map: InputMap,
+    held: HashSet<Binding>,
+    pressed: HashSet<Binding>,
+    released: HashSet<Binding>,
+    mouse_motion: (f32, f32),
+    mouse_position: (i32, i32),
+    mouse_wheel: f32,
+    /// By controller instance id, so unplugging one controller doesn't release what another holds
+    controller_buttons: HashMap<u32, HashSet<Button>>,
+    controller_axes: HashMap<u32, HashMap<Axis, f32>>,
+    /// When false `Mouse:X` and `Mouse:Y` read 0, e.g. while the cursor is not captured
+    pub mouse_look: bool,
+    /// Sticks closer than this to their center read as 0
+    pub stick_dead_zone: f32,
+    /// Triggers pressed less than this read as 0
+    pub trigger_dead_zone: f32,
+}
+
+impl Input {
+    pub fn new(map: InputMap) -> Input {
+        Input {
+            map,
+            held: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            mouse_motion: (0.0, 0.0),
+            mouse_position: (0, 0),
+            mouse_wheel: 0.0,
+            controller_buttons: HashMap::new(),
+            controller_axes: HashMap::new(),
+            mouse_look: true,
+            stick_dead_zone: 0.15,
+            trigger_dead_zone: 0.05,
+        }
+    }
+
+    /// Forgets the per-frame state (just pressed/released buttons, mouse motion)
+    pub fn begin_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.mouse_motion = (0.0, 0.0);
+        self.mouse_wheel = 0.0;
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            // Key repeats are not new presses
+            Event::KeyDown { scancode: Some(scancode), repeat: false, .. } => self.press(Binding::Key(*scancode)),
+            Event::KeyUp { scancode: Some(scancode), .. } => self.release(Binding::Key(*scancode)),
+            Event::MouseButtonDown { mouse_btn, x, y, .. } => {
+                self.mouse_position = (*x, *y);
+                self.press(Binding::Mouse(*mouse_btn));
+            }
+            Event::MouseButtonUp { mouse_btn, .. } => self.release(Binding::Mouse(*mouse_btn)),
+            Event::MouseMotion { x, y, xrel, yrel, .. } => {
+                self.mouse_position = (*x, *y);
+                self.move_mouse(*xrel as f32, *yrel as f32);
+            }
+            Event::MouseWheel { y, .. } => self.scroll(*y as f32),
+            Event::ControllerButtonDown { which, button, .. } => self.press_controller_button(*which, *button),
+            Event::ControllerButtonUp { which, button, .. } => self.release_controller_button(*which, *button),
+            Event::ControllerAxisMotion { which, axis, value, .. } => {
+                self.set_controller_axis(*which, *axis, (*value as f32 / i16::MAX as f32).max(-1.0))
+            }
+            // Don't keep a stick pushed or a button held once its controller is gone
+            Event::ControllerDeviceRemoved { which, .. } => self.reset_controller(*which),
+            _ => { }
+        }
+    }
+
+    pub fn press(&mut self, binding: Binding) {
+        if self.held.insert(binding) {
+            self.pressed.insert(binding);
+        }
+    }
+
+    pub fn release(&mut self, binding: Binding) {
+        if self.held.remove(&binding) {
+            self.released.insert(binding);
+        }
+    }
+
+    pub fn move_mouse(&mut self, dx: f32, dy: f32) {
+        self.mouse_motion.0 += dx;
+        self.mouse_motion.1 += dy;
+    }
+
+    /// Cursor position in window coordinates, meaningless while the mouse is captured
+    pub fn mouse_position(&self) -> (i32, i32) {
+        self.mouse_position
+    }
+
+    pub fn scroll(&mut self, steps: f32) {
+        self.mouse_wheel += steps;
+    }
+
+    /// `which` is the instance id of the controller
+    pub fn press_controller_button(&mut self, which: u32, button: Button) {
+        self.controller_buttons.entry(which).or_default().insert(button);
+        self.press(Binding::Controller(button));
+    }
+
+    /// The binding stays held while another controller holds the same button
+    pub fn release_controller_button(&mut self, which: u32, button: Button) {
+        if let Some(buttons) = self.controller_buttons.get_mut(&which) {
+            buttons.remove(&button);
+        }
+
+        if !self.controller_buttons.values().any(|buttons| buttons.contains(&button)) {
+            self.release(Binding::Controller(button));
+        }
+    }
+
+    pub fn set_controller_axis(&mut self, which: u32, axis: Axis, value: f32) {
+        self.controller_axes.entry(which).or_default().insert(axis, value);
+    }
+
+    /// Releases everything, e.g. when the window loses the focus
+    pub fn reset(&mut self) {
+        let held: Vec<Binding> = self.held.iter().copied().collect();
+        for binding in held {
+            self.release(binding);
+        }
+        self.controller_buttons.clear();
+        self.controller_axes.clear();
+    }
+
+    /// Releases the buttons of a controller and centers its sticks, the other controllers keep their state
+    pub fn reset_controller(&mut self, which: u32) {
+        let buttons = self.controller_buttons.remove(&which).unwrap_or_default();
+        for button in buttons {
+            self.release_controller_button(which, button);
+        }
+        self.controller_axes.remove(&which);
+    }
+
+    /// Value of a controller axis once the dead zone is removed, between -1 and 1
+    /// The stick dead zone is radial so diagonals are not snapped to the axes
+    /// With several controllers the one pushed the furthest wins
+    pub fn controller_axis(&self, axis: Axis) -> f32 {
+        self.controller_axes.values()
+            .map(|axes| self.dead_zoned_axis(axes, axis))
+            .fold(0.0, |value: f32, other| if other.abs() > value.abs() { other } else { value })
+    }
+
+    fn dead_zoned_axis(&self, axes: &HashMap<Axis, f32>, axis: Axis) -> f32 {
+        let raw = |axis: Axis| axes.get(&axis).copied().unwrap_or(0.0);
+
+        let other = match axis {
+            Axis::LeftX => Axis::LeftY,
+            Axis::LeftY => Axis::LeftX,
+            Axis::RightX => Axis::RightY,
+            Axis::RightY => Axis::RightX,
+            Axis::TriggerLeft | Axis::TriggerRight => {
+                return rescale_dead_zone(raw(axis), self.trigger_dead_zone);
+            }
+        };
+
+        let value = raw(axis);
+        let length = value.hypot(raw(other));
+        if length <= self.stick_dead_zone {
+            return 0.0;
+        }
+
+        // Scale the whole stick vector so its length goes from 0 at the dead zone to 1
+        value / length * rescale_dead_zone(length.min(1.0), self.stick_dead_zone)
+    }
+
+    /// True while any button bound to the action is held
+    pub fn is_pressed(&self, action: &str) -> bool {
+        self.map.action_bindings(action).iter().any(|binding| self.held.contains(binding))
+    }
+
+    /// True on the frame a button bound to the action goes down
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.map.action_bindings(action).iter().any(|binding| self.pressed.contains(binding))
+    }
+
+    /// True on the frame a button bound to the action goes up
+    pub fn just_released(&self, action: &str) -> bool {
+        self.map.action_bindings(action).iter().any(|binding| self.released.contains(binding))
+    }
+
+    /// Sum of every source bound to the axis
+    pub fn axis(&self, axis: &str) -> f32 {
+        self.map.axis_bindings(axis).iter().map(|binding| self.axis_binding_value(binding)).sum()
+    }
+
+    fn axis_binding_value(&self, binding: &AxisBinding) -> f32 {
+        match binding {
+            AxisBinding::Buttons { negative, positive } => {
+                let mut value = 0.0;
+                if self.held.contains(negative) { value -= 1.0; }
+                if self.held.contains(positive) { value += 1.0; }
+                value
+            }
+            AxisBinding::MouseX if self.mouse_look => self.mouse_motion.0,
+            AxisBinding::MouseY if self.mouse_look => self.mouse_motion.1,
+            AxisBinding::MouseX | AxisBinding::MouseY => 0.0,
+            AxisBinding::MouseWheel => self.mouse_wheel,
+            AxisBinding::Controller { axis, scale } => self.controller_axis(*axis) * scale,
+        }
+    }
+}
+
+fn rescale_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    if value.abs() <= dead_zone {
+        return 0.0;
+    }
+
+    value.signum() * (value.abs() - dead_zone) / (1.0 - dead_zone)
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::controller::{Axis, Button};
+    use sdl2::keyboard::Scancode;
+
+    use super::{AxisBinding, Binding, Input, InputMap, SCANCODES};
+
+    #[test]
+    fn every_key_round_trips() {
+        let keys: Vec<Scancode> = SCANCODES.into_iter().flatten().filter_map(Scancode::from_i32).collect();
+        assert!(keys.contains(&Scancode::KpHash) && keys.contains(&Scancode::Comma));
+
+        let mut map = InputMap::new();
+        for key in &keys {
+            map.bind_action("press", Binding::Key(*key));
+            map.bind_axis("move", AxisBinding::Buttons { negative: Binding::Key(*key), positive: Binding::Key(Scancode::KpDivide) });
+        }
+
+        let config = map.to_config();
+        let parsed = InputMap::parse(&config, "test").unwrap_or_else(|error| panic!("{}\n{}", error, config));
+
+        assert_eq!(parsed.action_bindings("press"), map.action_bindings("press"));
+        assert_eq!(parsed.axis_bindings("move"), map.axis_bindings("move"));
+        assert_eq!(parsed.to_config(), config);
+    }
+
+    #[test]
+    fn quoted_names() {
+        let map = InputMap::parse("action a = Key:\"Keypad #\", Key:\",\" # comment, Key:A\naxis b = Key:\"/\" / Key:\"\\\\\"", "test").unwrap();
+
+        assert_eq!(map.action_bindings("a"), &[Binding::Key(Scancode::KpHash), Binding::Key(Scancode::Comma)]);
+        assert_eq!(map.axis_bindings("b"), &[AxisBinding::Buttons {
+            negative: Binding::Key(Scancode::Slash),
+            positive: Binding::Key(Scancode::Backslash),
+        }]);
+    }
+
+    #[test]
+    fn unterminated_quote() {
+        assert!(InputMap::parse("action a = Key:\"Keypad #", "test").is_err());
+        assert!(InputMap::parse("action a = Key:\"A\"B", "test").is_err());
+    }
+
+    #[test]
+    fn unplugging_a_controller_keeps_the_others() {
+        let mut map = InputMap::new();
+        map.bind_action("jump", Binding::Controller(Button::A));
+        map.bind_action("fire", Binding::Controller(Button::B));
+        let mut input = Input::new(map);
+
+        input.press_controller_button(1, Button::A);
+        input.press_controller_button(2, Button::A);
+        input.press_controller_button(2, Button::B);
+        input.set_controller_axis(1, Axis::LeftX, 1.0);
+        input.set_controller_axis(2, Axis::LeftX, -0.5);
+        input.begin_frame();
+
+        input.reset_controller(2);
+
+        assert!(input.is_pressed("jump"));
+        assert!(!input.just_released("jump"));
+        assert!(!input.is_pressed("fire"));
+        assert!(input.just_released("fire"));
+        assert_eq!(input.controller_axis(Axis::LeftX), 1.0);
+
+        input.reset_controller(1);
+
+        assert!(!input.is_pressed("jump"));
+        assert_eq!(input.controller_axis(Axis::LeftX), 0.0);
+    }
+
+    #[test]
+    fn button_held_by_two_controllers() {
+        let mut map = InputMap::new();
+        map.bind_action("jump", Binding::Controller(Button::A));
+        let mut input = Input::new(map);
+
+        input.press_controller_button(1, Button::A);
+        input.press_controller_button(2, Button::A);
+        input.release_controller_button(1, Button::A);
+        assert!(input.is_pressed("jump"));
+
+        input.release_controller_button(2, Button::A);
+        assert!(!input.is_pressed("jump"));
+    }
+}
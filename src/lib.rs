@@ -0,0 +1,11 @@
+pub mod windsdl;
+
+pub mod input;
+
+pub mod gamepad;
+
+pub mod graphics;
+
+pub mod app;
+
+pub mod game;
@@ -1,60 +1,733 @@
-use sdl2::{EventPump, Sdl, VideoSubsystem};
-use sdl2::mouse::MouseUtil;
+use sdl2::{EventPump, GameControllerSubsystem, Sdl, VideoSubsystem};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::mouse::{Cursor, MouseUtil, SystemCursor};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::surface::Surface;
+use sdl2::rect::Rect;
+use sdl2::video::{DisplayMode, FullscreenType, GLContext, SwapInterval, Window, WindowPos};
+use std::ffi::CStr;
+use crate::graphics::debug::{self, DebugSeverity};
+use std::fmt;
+use std::fs;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::thread::ThreadId;
 
+/// Size in pixels of the area OpenGL draws into
+/// On HiDPI screens it is bigger than the window size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrawableSize {
+    pub width : u32,
+    pub height : u32,
+}
 
-use sdl2::video::{Window};
+/// Anything that has to follow the size of the window (camera, framebuffers...)
+pub trait ResizeListener {
+    fn on_resize(&mut self, size : DrawableSize);
+}
 
 pub struct Winsdl {
     pub sdl : Sdl,
     pub window : Window,
+    pub gl_context : GLContext,
+    pub gl : gl::Gl,
+    pub context_info : ContextInfo,
     pub event_pump: EventPump,
     pub video_subsystem: VideoSubsystem,
+    pub game_controller_subsystem: GameControllerSubsystem,
     pub mouse : MouseUtil,
     pub width : usize,
-    pub height : usize
+    pub height : usize,
+    drawable_size : DrawableSize,
+    mouse_captured : bool,
+    // SDL only keeps a pointer to the active cursor, it has to outlive its use
+    cursor : Option<Cursor>,
+    // Position and size to save while in fullscreen
+    windowed_rect : Rect,
 }
 
-impl Winsdl {
-    pub fn new(width : usize, height : usize) -> Result<Self, &'static str>{
-        let sdl : Sdl = sdl2::init().unwrap();
-        let video_subsystem : VideoSubsystem = sdl.video().unwrap();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowMode {
+    Windowed,
+    /// Fullscreen window at the desktop resolution, switching to it is instant
+    Borderless,
+    /// Changes the display mode of the screen
+    Fullscreen,
+}
+
+impl WindowMode {
+    fn name(&self) -> &'static str {
+        match self {
+            WindowMode::Windowed => "windowed",
+            WindowMode::Borderless => "borderless",
+            WindowMode::Fullscreen => "fullscreen",
+        }
+    }
 
+    fn from_name(name : &str) -> Option<WindowMode> {
+        match name {
+            "windowed" => Some(WindowMode::Windowed),
+            "borderless" => Some(WindowMode::Borderless),
+            "fullscreen" => Some(WindowMode::Fullscreen),
+            _ => None,
+        }
+    }
+}
 
-        let gl_attr = video_subsystem.gl_attr();
+/// A screen (monitor, projector...) and the modes it supports
+#[derive(Debug, Clone)]
+pub struct DisplayInfo {
+    pub index : i32,
+    pub name : String,
+    /// Position and size in the desktop coordinates
+    pub bounds : Rect,
+    /// Bounds without the task bars and docks
+    pub usable_bounds : Rect,
+    pub desktop_mode : DisplayMode,
+    pub modes : Vec<DisplayMode>,
+}
+
+/// Where the window was, saved between runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowPlacement {
+    pub mode : WindowMode,
+    pub display : i32,
+    pub x : i32,
+    pub y : i32,
+    pub width : u32,
+    pub height : u32,
+    pub maximized : bool,
+}
+
+impl WindowPlacement {
+    /// Reads the `key = value` lines written by `save`
+    pub fn load(path : &Path) -> Result<WindowPlacement, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let mut values = std::collections::HashMap::new();
+        for line in source.lines().filter(|line| !line.trim().is_empty()) {
+            let (key, value) = line.split_once('=')
+                .ok_or_else(|| format!("{}: expected 'key = value', got '{}'", path.display(), line))?;
+            values.insert(key.trim(), value.trim());
+        }
+
+        let value = |key : &str| values.get(key).copied()
+            .ok_or_else(|| format!("{}: missing '{}'", path.display(), key));
+        fn number<T : std::str::FromStr>(key : &str, value : &str) -> Result<T, String> {
+            value.parse::<T>().map_err(|_| format!("invalid {} '{}'", key, value))
+        }
+
+        Ok(WindowPlacement {
+            mode : WindowMode::from_name(value("mode")?)
+                .ok_or_else(|| format!("{}: unknown mode", path.display()))?,
+            display : number("display", value("display")?)?,
+            x : number("x", value("x")?)?,
+            y : number("y", value("y")?)?,
+            width : number("width", value("width")?)?,
+            height : number("height", value("height")?)?,
+            maximized : number("maximized", value("maximized")?)?,
+        })
+    }
+
+    pub fn save(&self, path : &Path) -> Result<(), String> {
+        let source = format!(
+            "mode = {}\ndisplay = {}\nx = {}\ny = {}\nwidth = {}\nheight = {}\nmaximized = {}\n",
+            self.mode.name(), self.display, self.x, self.y, self.width, self.height, self.maximized
+        );
+
+        fs::write(path, source).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+/// Settings of the window and of its OpenGL context
+pub struct WindowBuilder {
+    title : String,
+    width : usize,
+    height : usize,
+    /// Tried in order until a context can be created
+    gl_versions : Vec<(u8, u8)>,
+    depth_bits : u8,
+    stencil_bits : u8,
+    samples : u8,
+    srgb : bool,
+    debug : bool,
+    vsync : bool,
+    resizable : bool,
+    capture_mouse : bool,
+}
+
+impl WindowBuilder {
+    pub fn new(title : &str, width : usize, height : usize) -> WindowBuilder {
+        WindowBuilder {
+            title : String::from(title),
+            width,
+            height,
+            gl_versions : vec![(4, 5), (4, 1), (3, 3)],
+            depth_bits : 24,
+            stencil_bits : 8,
+            samples : 0,
+            srgb : false,
+            debug : false,
+            vsync : true,
+            resizable : true,
+            capture_mouse : false,
+        }
+    }
+
+    /// Preferred version first, e.g. `&[(4, 5), (3, 3)]`
+    pub fn gl_versions(mut self, versions : &[(u8, u8)]) -> WindowBuilder {
+        self.gl_versions = versions.to_vec();
+        self
+    }
+
+    pub fn depth_bits(mut self, bits : u8) -> WindowBuilder {
+        self.depth_bits = bits;
+        self
+    }
+
+    pub fn stencil_bits(mut self, bits : u8) -> WindowBuilder {
+        self.stencil_bits = bits;
+        self
+    }
+
+    /// MSAA samples of the default framebuffer, 0 to disable
+    pub fn samples(mut self, samples : u8) -> WindowBuilder {
+        self.samples = samples;
+        self
+    }
+
+    /// sRGB capable default framebuffer, GL_FRAMEBUFFER_SRGB is enabled when obtained
+    pub fn srgb(mut self, srgb : bool) -> WindowBuilder {
+        self.srgb = srgb;
+        self
+    }
+
+    /// Requests a debug context
+    pub fn debug(mut self, debug : bool) -> WindowBuilder {
+        self.debug = debug;
+        self
+    }
+
+    pub fn vsync(mut self, vsync : bool) -> WindowBuilder {
+        self.vsync = vsync;
+        self
+    }
 
+    pub fn resizable(mut self, resizable : bool) -> WindowBuilder {
+        self.resizable = resizable;
+        self
+    }
+
+    /// Starts in mouse-look mode, see `Winsdl::set_mouse_captured`
+    pub fn capture_mouse(mut self, capture : bool) -> WindowBuilder {
+        self.capture_mouse = capture;
+        self
+    }
+
+    pub fn build(self) -> Result<Winsdl, String> {
+        let sdl : Sdl = sdl2::init()?;
+        let video_subsystem : VideoSubsystem = sdl.video()?;
+        let game_controller_subsystem : GameControllerSubsystem = sdl.game_controller()?;
+
+        // The framebuffer attributes have to be set before creating the window
+        let gl_attr = video_subsystem.gl_attr();
         gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
-        gl_attr.set_context_version(4, 5);
+        gl_attr.set_double_buffer(true);
+        gl_attr.set_depth_size(self.depth_bits);
+        gl_attr.set_stencil_size(self.stencil_bits);
+        gl_attr.set_framebuffer_srgb_compatible(self.srgb);
+        if self.samples > 0 {
+            gl_attr.set_multisample_buffers(1);
+            gl_attr.set_multisample_samples(self.samples);
+        }
+        if self.debug {
+            gl_attr.set_context_flags().debug().set();
+        }
+
+        let mut window_builder = video_subsystem.window(&self.title, self.width as u32, self.height as u32);
+        window_builder.opengl().allow_highdpi();
+        if self.resizable {
+            window_builder.resizable();
+        }
+        let window : Window = window_builder.build().map_err(|e| e.to_string())?;
+
+        // Fall back to older versions when the driver doesn't support the preferred one
+        let mut gl_context = None;
+        let mut errors = Vec::new();
+        for &(major, minor) in &self.gl_versions {
+            gl_attr.set_context_version(major, minor);
+
+            match window.gl_create_context() {
+                Ok(context) => {
+                    gl_context = Some(context);
+                    break;
+                }
+                Err(e) => errors.push(format!("{}.{}: {}", major, minor, e)),
+            }
+        }
+        let gl_context = gl_context
+            .ok_or_else(|| format!("Failed to create an OpenGL context ({})", errors.join(", ")))?;
+
+        let gl = gl::Gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+        let context_info = ContextInfo::query(&gl);
 
-        let window : Window = video_subsystem
-            .window("My Window", width as u32, height as u32)
-            .opengl()
-            .resizable()
-            .build()
-            .unwrap();
+        // Driver messages, only debug contexts are guaranteed to send them
+        if self.debug && !debug::enable_debug_output(&gl, debug::default_logger(DebugSeverity::Low)) {
+            println!("KHR_debug is not supported, no OpenGL debug output");
+        }
 
+        if self.srgb {
+            gl.enable(gl::FRAMEBUFFER_SRGB);
+        }
+        if self.samples > 0 {
+            gl.enable(gl::MULTISAMPLE);
+        }
 
-        let mut mouse = video_subsystem.sdl().mouse();
+        let swap_interval = if self.vsync { SwapInterval::VSync } else { SwapInterval::Immediate };
+        if let Err(e) = video_subsystem.gl_set_swap_interval(swap_interval) {
+            println!("Failed to set the swap interval: {}", e);
+        }
 
-        mouse.set_relative_mouse_mode(true);
-        mouse.warp_mouse_in_window(&window, (width / 2) as i32, (height / 2) as i32);
+        let mouse = video_subsystem.sdl().mouse();
 
-        let event_pump : EventPump = sdl.event_pump().unwrap();
+        let event_pump : EventPump = sdl.event_pump()?;
 
+        let (drawable_width, drawable_height) = window.drawable_size();
 
-        return Ok(Winsdl {
+
+        let mut winsdl = Winsdl {
             sdl,
             window,
+            gl_context,
+            gl,
+            context_info,
             event_pump,
             video_subsystem,
+            game_controller_subsystem,
             mouse,
-            width,
-            height
-        });
+            width : self.width,
+            height : self.height,
+            drawable_size : DrawableSize { width : drawable_width, height : drawable_height },
+            mouse_captured : false,
+            cursor : None,
+            windowed_rect : Rect::new(0, 0, self.width as u32, self.height as u32),
+        };
+
+        winsdl.set_mouse_captured(self.capture_mouse);
+
+        Ok(winsdl)
+    }
+}
+
+/// What the driver actually gave, it can differ from what was asked
+#[derive(Debug, Clone)]
+pub struct ContextInfo {
+    pub major : i32,
+    pub minor : i32,
+    pub version : String,
+    pub renderer : String,
+    pub vendor : String,
+    pub samples : i32,
+    pub debug : bool,
+}
+
+impl ContextInfo {
+    fn query(gl : &gl::Gl) -> ContextInfo {
+        let string = |name : gl::types::GLenum| unsafe {
+            let pointer = gl.GetString(name);
+            if pointer.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr(pointer as *const _).to_string_lossy().into_owned()
+            }
+        };
+        let integer = |name : gl::types::GLenum| {
+            let mut value = 0;
+            unsafe { gl.GetIntegerv(name, &mut value); }
+            value
+        };
+
+        ContextInfo {
+            major : integer(gl::MAJOR_VERSION),
+            minor : integer(gl::MINOR_VERSION),
+            version : string(gl::VERSION),
+            renderer : string(gl::RENDERER),
+            vendor : string(gl::VENDOR),
+            samples : integer(gl::SAMPLES),
+            debug : integer(gl::CONTEXT_FLAGS) as gl::types::GLuint & gl::CONTEXT_FLAG_DEBUG_BIT != 0,
+        }
+    }
+}
+
+impl fmt::Display for ContextInfo {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OpenGL {}.{} ({}) on {} by {}", self.major, self.minor, self.version, self.renderer, self.vendor)?;
+        if self.samples > 0 {
+            write!(f, ", {}x MSAA", self.samples)?;
+        }
+        if self.debug {
+            write!(f, ", debug")?;
+        }
+        Ok(())
+    }
+}
+
+impl Winsdl {
+    /// Resizable window with the default settings, see `WindowBuilder` for the others
+    pub fn new(width : usize, height : usize) -> Result<Self, String>{
+        WindowBuilder::new("My Window", width, height).build()
+    }
+
+    pub fn drawable_size(&self) -> DrawableSize {
+        self.drawable_size
+    }
+
+    /// Follows `WindowEvent::Resized`/`SizeChanged`: updates the window size,
+    /// the GL viewport and notifies the listeners with the new drawable size
+    /// Returns the new size if the event resized the window
+    pub fn handle_resize(&mut self, gl : &gl::Gl, event : &Event, listeners : &mut [&mut dyn ResizeListener]) -> Option<DrawableSize> {
+        match event {
+            Event::Window { win_event: WindowEvent::Resized(..), .. }
+            | Event::Window { win_event: WindowEvent::SizeChanged(..), .. } => {}
+            _ => return None,
+        }
+
+        let (width, height) = self.window.size();
+        self.width = width as usize;
+        self.height = height as usize;
+
+        // SDL sends both events for most resizes
+        let (drawable_width, drawable_height) = self.window.drawable_size();
+        let size = DrawableSize { width : drawable_width, height : drawable_height };
+        if size == self.drawable_size {
+            return None;
+        }
+        self.drawable_size = size;
+
+        unsafe { gl.Viewport(0, 0, size.width as i32, size.height as i32); }
+
+        for listener in listeners.iter_mut() {
+            listener.on_resize(size);
+        }
+
+        Some(size)
+    }
+
+    /// Moves the cursor to the middle of the window
+    pub fn update_mouse_center(&self){
+        self.mouse.warp_mouse_in_window(&self.window, (self.width / 2) as i32, (self.height / 2) as i32)
+    }
+
+    /// Captured: the cursor is hidden and locked in the window, motion events only carry `xrel`/`yrel`
+    /// Released: the cursor is visible and moves freely
+    pub fn set_mouse_captured(&mut self, captured : bool) {
+        self.mouse.set_relative_mouse_mode(captured);
+        self.mouse.show_cursor(!captured);
+
+        // Release the cursor where the crosshair was
+        if !captured && self.mouse_captured {
+            self.update_mouse_center();
+        }
+
+        self.mouse_captured = captured;
+    }
+
+    pub fn is_mouse_captured(&self) -> bool {
+        self.mouse_captured
+    }
+
+    pub fn toggle_mouse_capture(&mut self) {
+        self.set_mouse_captured(!self.mouse_captured);
+    }
+
+    /// Cursor shown while the mouse is released, `hot_x`/`hot_y` being the clicking point
+    /// The image is expected top row first (not flipped like the textures)
+    pub fn set_cursor_image(&mut self, image : &image::RgbaImage, hot_x : i32, hot_y : i32) -> Result<(), String> {
+        let (width, height) = image.dimensions();
+        let mut pixels = image.as_raw().clone();
+
+        let surface = Surface::from_data(&mut pixels, width, height, width * 4, PixelFormatEnum::RGBA32)?;
+        let cursor = Cursor::from_surface(surface, hot_x, hot_y)?;
+
+        cursor.set();
+        self.cursor = Some(cursor);
+
+        Ok(())
+    }
+
+    pub fn set_system_cursor(&mut self, system_cursor : SystemCursor) -> Result<(), String> {
+        let cursor = Cursor::from_system(system_cursor)?;
+
+        cursor.set();
+        self.cursor = Some(cursor);
+
+        Ok(())
+    }
+
+    /// Converts window coordinates (mouse events) to drawable pixels (viewport, `Camera::screen_ray`)
+    pub fn window_to_drawable(&self, x : i32, y : i32) -> (f32, f32) {
+        (
+            x as f32 * self.drawable_size.width as f32 / self.width as f32,
+            y as f32 * self.drawable_size.height as f32 / self.height as f32,
+        )
+    }
+
+    pub fn window_mode(&self) -> WindowMode {
+        match self.window.fullscreen_state() {
+            FullscreenType::Off => WindowMode::Windowed,
+            FullscreenType::Desktop => WindowMode::Borderless,
+            FullscreenType::True => WindowMode::Fullscreen,
+        }
+    }
+
+    /// The window is resized, so `handle_resize` receives the new size
+    pub fn set_window_mode(&mut self, mode : WindowMode) -> Result<(), String> {
+        if self.window_mode() == WindowMode::Windowed && !self.window.is_maximized() {
+            self.windowed_rect = self.window_rect();
+        }
+
+        let fullscreen_type = match mode {
+            WindowMode::Windowed => FullscreenType::Off,
+            WindowMode::Borderless => FullscreenType::Desktop,
+            WindowMode::Fullscreen => FullscreenType::True,
+        };
+
+        self.window.set_fullscreen(fullscreen_type)
+    }
+
+    /// Windowed -> borderless -> fullscreen -> windowed
+    pub fn cycle_window_mode(&mut self) -> Result<(), String> {
+        let next = match self.window_mode() {
+            WindowMode::Windowed => WindowMode::Borderless,
+            WindowMode::Borderless => WindowMode::Fullscreen,
+            WindowMode::Fullscreen => WindowMode::Windowed,
+        };
+
+        self.set_window_mode(next)
+    }
+
+    /// Resolution and refresh rate used in `WindowMode::Fullscreen`, e.g. one of `DisplayInfo::modes`
+    pub fn set_fullscreen_display_mode(&mut self, mode : DisplayMode) -> Result<(), String> {
+        self.window.set_display_mode(Some(mode))
+    }
+
+    pub fn displays(&self) -> Result<Vec<DisplayInfo>, String> {
+        let video = &self.video_subsystem;
+
+        (0..video.num_video_displays()?).map(|index| {
+            let modes = (0..video.num_display_modes(index)?)
+                .map(|mode| video.display_mode(index, mode))
+                .collect::<Result<Vec<DisplayMode>, String>>()?;
+
+            Ok(DisplayInfo {
+                index,
+                name : video.display_name(index)?,
+                bounds : video.display_bounds(index)?,
+                usable_bounds : video.display_usable_bounds(index)?,
+                desktop_mode : video.desktop_display_mode(index)?,
+                modes,
+            })
+        }).collect()
+    }
+
+    /// Display the center of the window is on
+    pub fn current_display(&self) -> Result<i32, String> {
+        self.window.display_index()
+    }
+
+    /// Centers the window on another screen, fullscreen modes follow it
+    pub fn move_to_display(&mut self, display : i32) -> Result<(), String> {
+        let mode = self.window_mode();
+        if mode != WindowMode::Windowed {
+            self.set_window_mode(WindowMode::Windowed)?;
+        }
+
+        self.center_on_display(display)?;
+
+        self.set_window_mode(mode)
+    }
+
+    fn center_on_display(&mut self, display : i32) -> Result<(), String> {
+        let bounds = self.video_subsystem.display_bounds(display)?;
+        let (width, height) = self.window.size();
+        self.window.set_position(
+            WindowPos::Positioned(bounds.x() + (bounds.width() as i32 - width as i32) / 2),
+            WindowPos::Positioned(bounds.y() + (bounds.height() as i32 - height as i32) / 2),
+        );
+
+        Ok(())
+    }
+
+    fn window_rect(&self) -> Rect {
+        let (x, y) = self.window.position();
+        let (width, height) = self.window.size();
+        Rect::new(x, y, width, height)
+    }
+
+    pub fn placement(&self) -> WindowPlacement {
+        let mode = self.window_mode();
+        let maximized = mode == WindowMode::Windowed && self.window.is_maximized();
+
+        // Keep the size the window goes back to, not the fullscreen or maximized one
+        let rect = if mode == WindowMode::Windowed && !maximized { self.window_rect() } else { self.windowed_rect };
+
+        WindowPlacement {
+            mode,
+            display : self.current_display().unwrap_or(0),
+            x : rect.x(),
+            y : rect.y(),
+            width : rect.width(),
+            height : rect.height(),
+            maximized,
+        }
+    }
+
+    /// Puts the window back where it was
+    /// If it would be off screen it is centered on its saved display, or on the main one if that display is not connected anymore
+    pub fn restore_placement(&mut self, placement : &WindowPlacement) -> Result<(), String> {
+        self.set_window_mode(WindowMode::Windowed)?;
+        self.window.set_size(placement.width, placement.height).map_err(|e| e.to_string())?;
+
+        let rect = Rect::new(placement.x, placement.y, placement.width, placement.height);
+        let displays = self.video_subsystem.num_video_displays()?;
+        let on_screen = (0..displays)
+            .filter_map(|index| self.video_subsystem.display_bounds(index).ok())
+            .any(|bounds| bounds.has_intersection(rect));
+
+        if on_screen {
+            self.window.set_position(WindowPos::Positioned(placement.x), WindowPos::Positioned(placement.y));
+        } else if (0..displays).contains(&placement.display) {
+            self.center_on_display(placement.display)?;
+        } else {
+            self.window.set_position(WindowPos::Centered, WindowPos::Centered);
+        }
+        self.windowed_rect = self.window_rect();
+
+        if placement.maximized {
+            self.window.maximize();
+        }
+
+        self.set_window_mode(placement.mode)
+    }
+
+    /// Creates a context sharing its buffers, textures and programs with the window's one,
+    /// to upload resources from a loading thread
+    pub fn create_shared_context(&self) -> Result<SharedContext, String> {
+        use sdl2::sys::{SDL_CreateWindow, SDL_DestroyWindow, SDL_GL_CreateContext, SDL_WindowFlags};
+
+        // A context can only be made current with a window, this one has the pixel format of the main one
+        let flags = SDL_WindowFlags::SDL_WINDOW_OPENGL as u32 | SDL_WindowFlags::SDL_WINDOW_HIDDEN as u32;
+        let window = unsafe { SDL_CreateWindow(c"".as_ptr(), 0, 0, 1, 1, flags) };
+        if window.is_null() {
+            return Err(sdl2::get_error());
+        }
+
+        let gl_attr = self.video_subsystem.gl_attr();
+        gl_attr.set_share_with_current_context(true);
+        // Creating the context makes it current
+        let raw = unsafe { SDL_GL_CreateContext(window) };
+        gl_attr.set_share_with_current_context(false);
+        if raw.is_null() {
+            let error = sdl2::get_error();
+            unsafe { SDL_DestroyWindow(window); }
+            self.window.gl_make_current(&self.gl_context)?;
+            return Err(error);
+        }
+
+        let gl = self.gl.load_shared_with(|s| self.video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+        let shared = SharedContext {
+            window,
+            raw,
+            window_gl : self.gl.clone(),
+            gl,
+            main_thread : std::thread::current().id(),
+        };
+
+        // Not current anywhere once created, it can move to another thread
+        self.window.gl_make_current(&self.gl_context)?;
+        self.gl.mark_current();
+
+        Ok(shared)
+    }
+}
+
+impl Drop for Winsdl {
+    fn drop(&mut self) {
+        self.gl.mark_destroyed();
+    }
+}
+
+/// A GL context of the window's share group, meant to be moved to a loading thread
+///
+/// Buffers, textures and programs created with it can be used by the window's context once
+/// `CurrentContext::finish` returned and they are handed over with their `adopt` method
+/// Vertex arrays, framebuffers and queries are never shared, create them on the main thread
+///
+/// It is only current while the guard returned by `make_current` lives, so it can't move to another thread
+/// while current. Drop it on the main thread before the window
+pub struct SharedContext {
+    // Hidden, contexts need a window to be made current
+    window : *mut sdl2::sys::SDL_Window,
+    raw : sdl2::sys::SDL_GLContext,
+    // Context of the window, to know if it is still there
+    window_gl : gl::Gl,
+    pub gl : gl::Gl,
+    // SDL windows belong to the thread that created them
+    main_thread : ThreadId,
+}
+
+// The context is released before the guard making it current goes away, see `make_current`
+unsafe impl Send for SharedContext {}
+
+impl SharedContext {
+    /// Makes the context current on the calling thread until the guard is dropped
+    pub fn make_current(&self) -> Result<CurrentContext<'_>, String> {
+        if !self.window_gl.is_alive() {
+            return Err(String::from("the window's GL context was destroyed"));
+        }
+
+        if unsafe { sdl2::sys::SDL_GL_MakeCurrent(self.window, self.raw) } != 0 {
+            return Err(sdl2::get_error());
+        }
+        self.gl.mark_current();
+
+        Ok(CurrentContext { context : self, _thread : PhantomData })
+    }
+}
+
+impl Drop for SharedContext {
+    fn drop(&mut self) {
+        debug_assert!(
+            std::thread::current().id() == self.main_thread || std::thread::panicking(),
+            "SharedContext dropped outside the main thread"
+        );
+        self.gl.mark_destroyed();
+
+        // The window's context stays current, SDL only releases the deleted one
+        unsafe {
+            sdl2::sys::SDL_GL_DeleteContext(self.raw);
+            sdl2::sys::SDL_DestroyWindow(self.window);
+        }
     }
+}
 
-    pub fn update_mouse_center(&self, x : i32, y : i32){
-        self.mouse.warp_mouse_in_window(&self.window, (self.height / 2) as i32, (self.width / 2) as i32)
+/// A `SharedContext` current on this thread, released when dropped
+pub struct CurrentContext<'c> {
+    context : &'c SharedContext,
+    // Released on the thread it was made current on
+    _thread : PhantomData<*const ()>,
+}
 
+impl CurrentContext<'_> {
+    /// Waits for the driver to complete the uploads so the other contexts see finished objects
+    pub fn finish(&self) {
+        unsafe { self.context.gl.Finish(); }
     }
 }
 
+impl Drop for CurrentContext<'_> {
+    fn drop(&mut self) {
+        unsafe { sdl2::sys::SDL_GL_MakeCurrent(self.context.window, std::ptr::null_mut()); }
+        self.context.gl.mark_released();
+    }
+}